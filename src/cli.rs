@@ -10,27 +10,55 @@ pub mod environment;
 use clap::Parser;
 
 use crate::{
-    cli::interface::{Cli, Command},
-    platform::{ColorModeDaemon, ColorModeDetector},
+    cli::{
+        commands::{RunContext, Selection, Trigger},
+        interface::{Cli, Command},
+    },
+    config::{Config, DetectorKind},
+    mode::Appearance,
+    platform::backend,
 };
 
-pub async fn run<F, Futu, Daemon>(native_adapter: F) -> anyhow::Result<()>
-where
-    Futu: std::future::Future<Output = anyhow::Result<Daemon>>,
-    F: FnOnce() -> Futu,
-    Daemon: ColorModeDaemon + ColorModeDetector,
-{
+pub async fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let command = cli
-        .command
-        .unwrap_or_else(|| Command::Daemon { verbose: false });
+    let command = cli.command.unwrap_or(Command::Daemon { verbose: false });
+    let config = Config::load()?;
+    let detector = cli.detector.unwrap_or(config.detector);
 
     match command {
-        Command::Daemon { verbose } => commands::daemon(native_adapter, verbose).await?,
+        Command::Daemon { verbose } => {
+            let backend = backend::select(detector, &config).await?;
+            commands::daemon(backend, config, verbose).await?
+        }
         Command::Current { watch, plain } => {
-            commands::current(native_adapter, watch, plain).await?
+            let backend = backend::select(detector, &config).await?;
+            commands::current(backend, watch, plain).await?
+        }
+        Command::Wait { mode, timeout } => {
+            let backend = backend::select(detector, &config).await?;
+            commands::wait(backend, mode, timeout).await?
+        }
+        Command::Exec { command } => {
+            let backend = backend::select(detector, &config).await?;
+            commands::exec(backend, command).await?
+        }
+        Command::Run {
+            mode,
+            verbose,
+            only,
+            exclude,
+            dry_run,
+        } => {
+            // The mode comes from the command line, so no detector is
+            // involved and none is set up just to name it in DMD_DETECTOR.
+            let context = RunContext::new(Appearance::from(mode), Trigger::Manual);
+            let selection = Selection::new(&only, &exclude)?;
+            if dry_run {
+                commands::dry_run(context, &config, &selection);
+            } else {
+                commands::run(context, &config, &selection, verbose, true);
+            }
         }
-        Command::Run { mode, verbose } => commands::run(mode, verbose, true),
         Command::Autostart { command } => {
             #[cfg(target_os = "linux")]
             crate::platform::linux::autostart::handle_autostart_command(command)?;
@@ -38,7 +66,25 @@ where
             #[cfg(target_os = "macos")]
             crate::platform::macos::autostart::handle_autostart_command(command)?;
         }
-        Command::List { resolve, verbose } => commands::list(resolve, verbose)?,
+        Command::List { resolve, verbose } => commands::list(&config, resolve, verbose)?,
+
+        // These either talk to the running daemon, or change the color scheme
+        // of the operating system, even when the daemon follows a schedule.
+        Command::Set { mode } => commands::set(mode).await?,
+        Command::Toggle => {
+            let backend = backend::select(DetectorKind::Native, &config).await?;
+            commands::toggle(backend).await?
+        }
+        Command::Override {
+            mode,
+            duration,
+            until_next_change,
+            clear,
+        } => commands::override_mode(mode, duration, until_next_change, clear).await?,
+        Command::Pause { duration } => commands::pause(duration).await?,
+        Command::Resume { no_run } => commands::resume(!no_run).await?,
+        Command::Status => commands::status().await?,
+        Command::History => commands::history().await?,
     };
 
     Ok(())
@@ -1,9 +1,30 @@
 mod current;
 mod daemon;
+mod exec;
+mod history;
 mod list;
+mod override_mode;
+mod pause;
+mod resume;
 mod run;
+mod set;
+mod status;
+mod toggle;
+mod wait;
 
 pub use current::current;
 pub use daemon::daemon;
+pub use exec::exec;
+pub use history::history;
 pub use list::list;
-pub use run::run;
+pub use override_mode::override_mode;
+pub use pause::pause;
+pub use resume::resume;
+pub use run::{
+    dry_run, run, run_completion_hooks, run_lifecycle_hooks, Attempt, RunContext, RunReport,
+    ScriptKind, ScriptOutcome, ScriptResult, Selection, Trigger,
+};
+pub use set::set;
+pub use status::status;
+pub use toggle::toggle;
+pub use wait::wait;
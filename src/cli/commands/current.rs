@@ -1,35 +1,55 @@
-use crate::platform::{ColorModeDaemon, ColorModeDetector};
+use crate::{
+    ipc::{self, Request, Response},
+    mode::{Appearance, Contrast},
+    platform::{backend::ColorModeBackend, ColorModeEvent},
+};
 
-pub async fn current<F, Futu, Daemon>(
-    native_adapter: F,
+pub async fn current(
+    backend: Box<dyn ColorModeBackend>,
     watch: bool,
     plain: bool,
-) -> anyhow::Result<()>
-where
-    Futu: std::future::Future<Output = anyhow::Result<Daemon>>,
-    F: FnOnce() -> Futu,
-    Daemon: ColorModeDaemon + ColorModeDetector,
-{
+) -> anyhow::Result<()> {
     // FIXME: error handling
-    let adapter = native_adapter().await?;
-    let mode = adapter.current_mode().await.unwrap();
-    if plain {
-        println!("{}", mode);
-    } else {
-        println!("{} {}", mode.emoji(), mode);
+    let appearance = backend.current_appearance().await.unwrap();
+    print_appearance(appearance, plain);
+    if !plain {
+        print_active_override().await;
     }
     if !watch {
         return Ok(());
     }
 
-    adapter
-        .on_color_changed(move |mode| {
-            if plain {
-                println!("{}", mode);
-            } else {
-                println!("{} {}", mode.emoji(), mode);
+    backend
+        .start(Box::new(move |event| {
+            if let ColorModeEvent::AppearanceChanged(appearance) = event {
+                print_appearance(appearance, plain);
             }
-        })
+        }))
         .await;
     Ok(())
 }
+
+fn print_appearance(appearance: Appearance, plain: bool) {
+    let mode = appearance.mode;
+    if plain {
+        println!("{}", mode);
+    } else if appearance.contrast == Contrast::High {
+        println!("{} {} (high contrast)", mode.emoji(), mode);
+    } else {
+        println!("{} {}", mode.emoji(), mode);
+    }
+}
+
+/// Mentions when a running daemon ignores the current mode for its scripts.
+async fn print_active_override() {
+    let Ok(Response::Status(status)) = ipc::send(Request::Status).await else {
+        return;
+    };
+
+    if let Some(active_override) = status.active_override {
+        println!(
+            "🔒 Scripts are overridden to {} {}",
+            active_override.mode, active_override.expiry
+        );
+    }
+}
@@ -1,23 +1,93 @@
+use std::sync::Arc;
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::mpsc,
+};
+
 use crate::{
-    cli::commands::run,
-    platform::{ColorModeDaemon, ColorModeDetector},
+    cli::commands::{
+        run, run_completion_hooks, run_lifecycle_hooks, RunContext, Selection, Trigger,
+    },
+    config::{Config, HookEvent},
+    daemon::{Event, State},
+    ipc,
+    notifications::Notifier,
+    platform::backend::ColorModeBackend,
+    sinks::Sinks,
 };
 
-pub async fn daemon<F, Futu, Daemon>(native_adapter: F, verbose: bool) -> anyhow::Result<()>
-where
-    Futu: std::future::Future<Output = anyhow::Result<Daemon>>,
-    F: FnOnce() -> Futu,
-    Daemon: ColorModeDaemon + ColorModeDetector,
-{
-    let adapter = native_adapter().await?;
+pub async fn daemon(
+    backend: Box<dyn ColorModeBackend>,
+    config: Config,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    let listener = ipc::listen().await?;
+
     println!("😈 Running scripts initially for current color mode...");
     // FIXME: Actually handle errors here
-    let mode = adapter.current_mode().await.unwrap();
-    run(mode, verbose, true);
+    let appearance = backend.current_appearance().await.unwrap();
+    // Scripts can do without the accent color, so it is not worth failing for.
+    let accent_color = backend
+        .current_accent_color()
+        .await
+        .unwrap_or_else(|error| {
+            println!("⚠️ Could not read the accent color: {error}");
+            None
+        });
+    let context = RunContext {
+        accent_color,
+        detector: Some(backend.name()),
+        ..RunContext::new(appearance, Trigger::Startup)
+    };
+    let config = Arc::new(config);
+    let sinks = Sinks::connect(&config);
+    let mut notifier = Notifier::new(config.notifications);
+    // Scripts are run synchronously, so we don't block the other tasks.
+    let report = tokio::task::spawn_blocking({
+        let config = config.clone();
+        move || {
+            run_lifecycle_hooks(HookEvent::OnStart, &context, None, &config, verbose);
+            let report = run(context, &config, &Selection::default(), verbose, true);
+            run_completion_hooks(&context, &report, &config, verbose);
+            report
+        }
+    })
+    .await?;
+    sinks.publish(&context, report.run_id);
+    notifier.report(&report, appearance.mode).await;
 
     println!("😈 Spawning daemon...");
-    adapter
-        .on_color_changed(move |mode| run(mode, verbose, true))
-        .await;
+    let (events, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(ipc::serve(listener, events.clone()));
+    let state = State::new(context, backend.kind(), config, notifier, sinks, verbose);
+    #[cfg(target_os = "linux")]
+    let _service =
+        match crate::platform::linux::service::serve(events.clone(), state.subscribe()).await {
+            Ok(connection) => Some(connection),
+            Err(error) => {
+                println!("⚠️ Could not offer the D-Bus service: {error}");
+                None
+            }
+        };
+    let state = tokio::spawn(state.run(receiver));
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let detected = events.clone();
+    let detect = backend.start(Box::new(move |event| {
+        let _ = detected.send(Event::Detected(event));
+    }));
+    tokio::select! {
+        _ = detect => println!("⚠️ Stopped detecting color mode changes"),
+        _ = interrupt.recv() => {}
+        _ = terminate.recv() => {}
+    }
+
+    println!("😈 Shutting down...");
+    backend.shutdown();
+    let _ = events.send(Event::Shutdown);
+    let _ = state.await;
+    ipc::unlisten();
     Ok(())
 }
@@ -0,0 +1,87 @@
+use std::{ffi::OsString, os::unix::process::CommandExt, path::Path, process::Command};
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use crate::{
+    cli::commands::{RunContext, Trigger},
+    discovery::ScriptsDirectory,
+    platform::backend::ColorModeBackend,
+};
+
+/// Replaces this process with the `command`, passing it the environment
+/// variables scripts receive for the current mode.
+pub async fn exec(
+    backend: Box<dyn ColorModeBackend>,
+    command: Vec<OsString>,
+) -> anyhow::Result<()> {
+    let Some((program, arguments)) = command.split_first() else {
+        return Err(anyhow!("No command to run"));
+    };
+
+    let scripts_directory = ScriptsDirectory::read()
+        .map(|directory| directory.path)
+        .unwrap_or_default();
+    let environment = environment(backend.as_ref(), &scripts_directory).await?;
+
+    // Only returns if the command could not be started.
+    let error = Command::new(program)
+        .args(arguments)
+        .envs(environment)
+        .exec();
+    Err(anyhow!(
+        "Could not run '{}': {error}",
+        program.to_string_lossy()
+    ))
+}
+
+/// The environment variables scripts would receive for the current mode of
+/// the `backend`.
+async fn environment(
+    backend: &dyn ColorModeBackend,
+    scripts_directory: &Path,
+) -> anyhow::Result<Vec<(&'static str, String)>> {
+    let context = RunContext {
+        accent_color: backend.current_accent_color().await?,
+        detector: Some(backend.name()),
+        ..RunContext::new(backend.current_appearance().await?, Trigger::Manual)
+    };
+
+    Ok(context.environment(Uuid::new_v4(), scripts_directory))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mode::{AccentColor, ColorMode},
+        platform::backend::fake::FakeBackend,
+    };
+
+    #[tokio::test]
+    async fn passes_the_current_mode() {
+        let backend = FakeBackend {
+            accent_color: Some(AccentColor {
+                red: 0x35,
+                green: 0x84,
+                blue: 0xe4,
+            }),
+            ..FakeBackend::new(ColorMode::Dark)
+        };
+
+        let environment = environment(&backend, Path::new("/scripts")).await.unwrap();
+
+        let variable = |name| {
+            environment
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(variable("DMD_COLOR_MODE"), Some("dark"));
+        assert_eq!(variable("DMD_ACCENT_COLOR"), Some("#3584e4"));
+        assert_eq!(variable("DMD_TRIGGER"), Some("manual"));
+        assert_eq!(variable("DMD_DETECTOR"), Some("fake"));
+        assert_eq!(variable("DMD_SCRIPTS_DIR"), Some("/scripts"));
+        assert_eq!(variable("DMD_PREVIOUS_COLOR_MODE"), None);
+    }
+}
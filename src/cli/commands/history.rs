@@ -0,0 +1,24 @@
+use anyhow::anyhow;
+use chrono::Local;
+
+use crate::ipc::{self, Request, Response};
+
+pub async fn history() -> anyhow::Result<()> {
+    let entries = match ipc::send(Request::History).await? {
+        Response::History { entries } => entries,
+        Response::Error { message } => return Err(anyhow!(message)),
+        response => return Err(anyhow!("Unexpected response from daemon: {response:?}")),
+    };
+
+    for entry in entries {
+        println!(
+            "{} {} {} ({})",
+            entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            entry.mode.emoji(),
+            entry.mode,
+            entry.outcome
+        );
+    }
+
+    Ok(())
+}
@@ -1,9 +1,17 @@
 use std::process::exit;
 
 use crate::cli::environment::Environment;
+use crate::config::{Config, HookEvent};
 use crate::discovery::{ScriptsDirectory, ScriptsDirectoryEntryKind};
 
-pub fn list(resolve: bool, verbose: bool) -> anyhow::Result<()> {
+const LIFECYCLE_EVENTS: [HookEvent; 4] = [
+    HookEvent::OnStart,
+    HookEvent::OnStop,
+    HookEvent::OnSuccess,
+    HookEvent::OnFailure,
+];
+
+pub fn list(config: &Config, resolve: bool, verbose: bool) -> anyhow::Result<()> {
     let environment = Environment::infer();
     let scripts_directory = match ScriptsDirectory::read() {
         Ok(directory) => directory,
@@ -48,6 +56,56 @@ pub fn list(resolve: bool, verbose: bool) -> anyhow::Result<()> {
             }
             ScriptsDirectoryEntryKind::Script => {
                 println!("{}", path.to_string_lossy());
+                if verbose {
+                    let metadata = entry.metadata.to_string();
+                    if !metadata.is_empty() {
+                        println!("  🏷️ {metadata}");
+                    }
+                    for warning in &entry.metadata.warnings {
+                        println!("  ⚠️ {warning}");
+                    }
+                }
+            }
+        }
+    }
+
+    for event in LIFECYCLE_EVENTS {
+        let Ok(directory) = ScriptsDirectory::read_hooks(event) else {
+            continue;
+        };
+
+        for entry in directory.flatten() {
+            if matches!(entry.kind, ScriptsDirectoryEntryKind::Script) {
+                let path = if resolve { entry.target } else { entry.script };
+                println!("{} ({event} hook)", path.to_string_lossy());
+            }
+        }
+    }
+
+    if !config.hooks.is_empty() && !environment.piped {
+        println!("\n🪝 Hooks from the config...\n");
+    }
+
+    for hook in &config.hooks {
+        match hook.event {
+            HookEvent::Run => println!("{} (hook)", hook.name()),
+            event => println!("{} ({event} hook)", hook.name()),
+        }
+        if verbose {
+            let command = if hook.shell {
+                format!("sh -c '{}'", hook.command)
+            } else {
+                [&hook.command]
+                    .into_iter()
+                    .chain(&hook.args)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            println!("  $ {command}");
+            if let Some(modes) = &hook.modes {
+                let modes: Vec<_> = modes.iter().map(ToString::to_string).collect();
+                println!("  🏷️ modes={}", modes.join(","));
             }
         }
     }
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use crate::{
+    cli::commands::{pause::from_now, status::request_status},
+    ipc::{Override, OverrideExpiry, Request},
+    mode::ColorMode,
+};
+
+pub async fn override_mode(
+    mode: Option<ColorMode>,
+    duration: Option<Duration>,
+    until_next_change: bool,
+    clear: bool,
+) -> anyhow::Result<()> {
+    let request = match mode {
+        Some(mode) if !clear => {
+            let expiry = match duration {
+                Some(duration) if !until_next_change => OverrideExpiry::At(from_now(duration)?),
+                // Lasting until the next change is also the default.
+                _ => OverrideExpiry::NextChange,
+            };
+            Request::Override(Override { mode, expiry })
+        }
+        _ => Request::ClearOverride,
+    };
+
+    request_status(request).await
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    cli::commands::status::request_status,
+    ipc::{Pause, Request},
+};
+
+pub async fn pause(duration: Option<Duration>) -> anyhow::Result<()> {
+    let until = duration.map(from_now).transpose()?;
+
+    request_status(Request::Pause(Pause { until })).await
+}
+
+/// The point in time `duration` from now, unless that is too far in the
+/// future to represent.
+pub(super) fn from_now(duration: Duration) -> anyhow::Result<DateTime<Utc>> {
+    TimeDelta::from_std(duration)
+        .ok()
+        .and_then(|delta| Utc::now().checked_add_signed(delta))
+        .ok_or_else(|| anyhow!("{} is too long", humantime::format_duration(duration)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_durations_too_long_to_represent() {
+        let in_an_hour = from_now(Duration::from_secs(60 * 60)).unwrap();
+        assert!(in_an_hour > Utc::now());
+
+        let error = from_now(Duration::from_secs(u64::MAX)).unwrap_err();
+        assert!(error.to_string().ends_with("is too long"), "{error}");
+    }
+}
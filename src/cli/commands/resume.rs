@@ -0,0 +1,5 @@
+use crate::{cli::commands::status::request_status, ipc::Request};
+
+pub async fn resume(run_scripts: bool) -> anyhow::Result<()> {
+    request_status(Request::Resume { run_scripts }).await
+}
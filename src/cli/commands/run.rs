@@ -1,99 +1,756 @@
+/// Printing what a run would do, without doing it.
+mod dry_run;
+/// Hooks for the daemon starting, stopping and finishing a run.
+mod lifecycle;
+/// Choosing scripts and hooks of a run by name.
+mod selection;
+
 use std::{
-    path::PathBuf,
-    process::{exit, Child, Command, Stdio},
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, HookConfig, HookEvent, RetryConfig, ScriptInput},
+    discovery::{
+        metadata::OnFailure, ScriptsDirectory, ScriptsDirectoryEntry, ScriptsDirectoryEntryError,
+        ScriptsDirectoryEntryKind, ScriptsDirectoryError, Stage,
+    },
+    mode::{AccentColor, Appearance, ColorMode},
 };
 
-use crate::{discovery::ScriptsDirectoryEntryKind, mode::ColorMode};
+pub use dry_run::dry_run;
+pub use lifecycle::{run_completion_hooks, run_lifecycle_hooks};
+pub use selection::Selection;
+
+/// Why the scripts are run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// The daemon just started.
+    Startup,
+
+    /// The detector noticed a change.
+    Change,
+
+    /// Someone ran `dark-mode-daemon run` or `exec`, or asked the daemon to
+    /// run the scripts again.
+    Manual,
+
+    /// An override started or ended.
+    Override,
+
+    /// The schedule switched the mode.
+    Schedule,
+
+    /// The daemon was resumed after a pause.
+    Resume,
+
+    /// The daemon is shutting down.
+    Shutdown,
+}
+
+impl Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let trigger = match self {
+            Trigger::Startup => "startup",
+            Trigger::Change => "change",
+            Trigger::Manual => "manual",
+            Trigger::Override => "override",
+            Trigger::Schedule => "schedule",
+            Trigger::Resume => "resume",
+            Trigger::Shutdown => "shutdown",
+        };
+
+        write!(f, "{trigger}")
+    }
+}
+
+/// A [`RunContext`] as scripts receive it on stdin.
+#[derive(Serialize)]
+struct Document {
+    run_id: Uuid,
+    trigger: Trigger,
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    appearance: Appearance,
+    previous_mode: Option<ColorMode>,
+    accent_color: Option<String>,
+    accent_color_only: bool,
+    detector: Option<&'static str>,
+}
+
+/// What the scripts are run for.
+#[derive(Clone, Copy)]
+pub struct RunContext {
+    pub appearance: Appearance,
+    pub accent_color: Option<AccentColor>,
+
+    /// Whether nothing but the accent color changed, in which case only the
+    /// scripts that opted into accent color changes are run.
+    pub accent_color_only: bool,
+
+    /// The mode the scripts were run for last time, if known.
+    pub previous_mode: Option<ColorMode>,
+
+    pub trigger: Trigger,
+
+    /// The name of the detector, unless the mode was chosen manually.
+    pub detector: Option<&'static str>,
+}
+
+impl RunContext {
+    pub fn new(appearance: Appearance, trigger: Trigger) -> Self {
+        Self {
+            appearance,
+            accent_color: None,
+            accent_color_only: false,
+            previous_mode: None,
+            trigger,
+            detector: None,
+        }
+    }
+
+    /// The JSON document scripts receive on stdin if they ask for it, which
+    /// is also sent to webhooks.
+    pub fn payload(&self, run_id: Uuid) -> String {
+        serde_json::to_string(&self.document(run_id)).unwrap_or_default()
+    }
+
+    fn document(&self, run_id: Uuid) -> Document {
+        Document {
+            run_id,
+            trigger: self.trigger,
+            timestamp: Utc::now(),
+            appearance: self.appearance,
+            previous_mode: self.previous_mode,
+            accent_color: self.accent_color.map(|color| color.to_string()),
+            accent_color_only: self.accent_color_only,
+            detector: self.detector,
+        }
+    }
+
+    /// The environment variables every script receives.
+    pub fn environment(
+        &self,
+        run_id: Uuid,
+        scripts_directory: &Path,
+    ) -> Vec<(&'static str, String)> {
+        let appearance = self.appearance;
+        let mut environment = vec![
+            ("DMD_COLOR_MODE", appearance.mode.to_string()),
+            ("DMD_COLOR_PREFERENCE", appearance.preference.to_string()),
+            ("DMD_CONTRAST", appearance.contrast.to_string()),
+            ("DMD_TRIGGER", self.trigger.to_string()),
+            ("DMD_RUN_ID", run_id.to_string()),
+            (
+                "DMD_SCRIPTS_DIR",
+                scripts_directory.to_string_lossy().to_string(),
+            ),
+        ];
+        if let Some(accent_color) = self.accent_color {
+            environment.push(("DMD_ACCENT_COLOR", accent_color.to_string()));
+        }
+        if let Some(previous_mode) = self.previous_mode {
+            environment.push(("DMD_PREVIOUS_COLOR_MODE", previous_mode.to_string()));
+        }
+        if let Some(detector) = self.detector {
+            environment.push(("DMD_DETECTOR", detector.to_string()));
+        }
+
+        environment
+    }
+}
+
+/// What happened to every script and hook of a run.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunReport {
+    pub run_id: Uuid,
+    pub results: Vec<ScriptResult>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScriptResult {
+    pub kind: ScriptKind,
+
+    /// The path of a script, or the name of a hook.
+    pub name: String,
+
+    /// How the last attempt went.
+    pub outcome: ScriptOutcome,
+
+    /// Every time the script was run. Empty if it was skipped.
+    pub attempts: Vec<Attempt>,
+
+    /// Whether the script asked for a desktop notification when it fails.
+    pub notify: bool,
+}
+
+/// A single run of a script, which may be retried when it fails.
+#[derive(Clone, Debug, Serialize)]
+pub struct Attempt {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u128,
+    pub outcome: ScriptOutcome,
+
+    /// The exit code, unless the script was not started, timed out or was
+    /// killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptKind {
+    /// An executable in the scripts directory.
+    Script,
+
+    /// A command from the `[[hooks]]` of the config.
+    Hook,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptOutcome {
+    Succeeded,
+    Failed,
+    TimedOut,
+
+    /// Not run, because an earlier stage failed.
+    Skipped,
+}
+
+impl RunReport {
+    /// Whether a script failed or timed out.
+    pub fn failed(&self) -> bool {
+        self.count(ScriptOutcome::Failed) + self.count(ScriptOutcome::TimedOut) > 0
+    }
+
+    fn count(&self, outcome: ScriptOutcome) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.outcome == outcome)
+            .count()
+    }
+}
+
+impl Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failed = self.count(ScriptOutcome::Failed) + self.count(ScriptOutcome::TimedOut);
+        write!(
+            f,
+            "{} succeeded, {failed} failed",
+            self.count(ScriptOutcome::Succeeded)
+        )?;
+
+        let skipped = self.count(ScriptOutcome::Skipped);
+        if skipped > 0 {
+            write!(f, ", {skipped} skipped")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How often we check whether one of the running scripts finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Everything needed to run a script or hook.
+struct Script {
+    kind: ScriptKind,
+    name: String,
+    program: PathBuf,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+
+    /// Whether no other script may run at the same time.
+    exclusive: bool,
+
+    timeout: Option<Duration>,
+    on_failure: OnFailure,
+    stdin: ScriptInput,
+    retry: RetryConfig,
+    attempts: Vec<Attempt>,
+}
+
+impl Script {
+    fn from_entry(entry: ScriptsDirectoryEntry, config: &Config, on_failure: OnFailure) -> Self {
+        let name = entry.script.file_name().unwrap_or_default();
+        let script_config = config.script(&name.to_string_lossy());
+        let metadata = entry.metadata;
+
+        Self {
+            kind: ScriptKind::Script,
+            name: entry.target.to_string_lossy().to_string(),
+            program: entry.target,
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            exclusive: script_config.exclusive || metadata.exclusive,
+            timeout: metadata.timeout,
+            on_failure: metadata.on_failure.unwrap_or(on_failure),
+            stdin: metadata.stdin.or(script_config.stdin).unwrap_or_default(),
+            retry: metadata.retry(script_config.retry.unwrap_or(config.retry)),
+            attempts: Vec::new(),
+        }
+    }
+
+    fn from_hook(hook: &HookConfig, on_failure: OnFailure, retry: RetryConfig) -> Self {
+        let (program, args) = if hook.shell {
+            ("sh".into(), vec!["-c".to_string(), hook.command.clone()])
+        } else {
+            (hook.command.clone().into(), hook.args.clone())
+        };
+
+        Self {
+            kind: ScriptKind::Hook,
+            name: hook.name(),
+            program,
+            args,
+            env: hook.env.clone().into_iter().collect(),
+            cwd: hook.cwd.clone(),
+            exclusive: false,
+            timeout: None,
+            on_failure,
+            stdin: hook.stdin,
+            retry: hook.retry.unwrap_or(retry),
+            attempts: Vec::new(),
+        }
+    }
+
+    fn result(self, outcome: ScriptOutcome) -> ScriptResult {
+        ScriptResult {
+            kind: self.kind,
+            name: self.name,
+            outcome,
+            attempts: self.attempts,
+            notify: self.on_failure == OnFailure::Notify,
+        }
+    }
+
+    /// How long to wait before trying again after the last attempt failed,
+    /// if at all.
+    fn retry_delay(&self) -> Option<Duration> {
+        let attempt = self.attempts.len() as u32;
+        if attempt >= self.retry.attempts.get() {
+            return None;
+        }
+
+        Some(self.retry.delay_after(attempt))
+    }
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ScriptKind::Script => write!(f, "Script '{}'", self.name),
+            ScriptKind::Hook => write!(f, "Hook '{}'", self.name),
+        }
+    }
+}
+
+/// What every script of a run receives.
+struct Input {
+    environment: Vec<(&'static str, String)>,
+    payload: String,
+}
 
 struct ScriptExecution {
-    script: PathBuf,
+    script: Script,
     process: Child,
+    started: Instant,
+    started_at: DateTime<Utc>,
 }
 
-pub fn run(mode: ColorMode, verbose: bool, pipe_stdio: bool) {
-    let scripts_directory = match crate::discovery::ScriptsDirectory::read() {
-        Ok(directory) => directory,
-        Err(error) => {
-            // TODO: we can probably exit 0 if the directory is just missing.
-            println!("ScriptsDirectoryError: {error:?}");
-            exit(-1);
+/// The scripts and hooks a run executes, by stage, and everything it skips.
+struct Plan {
+    /// Where the scripts are, unless there is no scripts directory.
+    scripts_directory: Option<PathBuf>,
+    stages: BTreeMap<Stage, Vec<Script>>,
+    skipped: Vec<Skipped>,
+}
+
+/// A file or hook that is not run, and why.
+struct Skipped {
+    /// The quoted path of a file, or the name of a hook.
+    name: String,
+    reason: String,
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}", self.name, self.reason)
+    }
+}
+
+impl From<ScriptsDirectoryEntryError> for Skipped {
+    fn from(error: ScriptsDirectoryEntryError) -> Self {
+        match error {
+            ScriptsDirectoryEntryError::CouldNotStartIterating => Self {
+                name: "An entry of the scripts directory".to_string(),
+                reason: "it could not be read".to_string(),
+            },
+            ScriptsDirectoryEntryError::FailedToReadMetadata { source, error } => Self {
+                name: format!("'{}'", source.to_string_lossy()),
+                reason: format!("its metadata could not be read: {error}"),
+            },
+            ScriptsDirectoryEntryError::FailedToResolveSymlink { source, error } => Self {
+                name: format!("'{}'", source.to_string_lossy()),
+                reason: format!("the symlink could not be resolved: {error}"),
+            },
         }
+    }
+}
+
+/// Decides which scripts and hooks run for the `context`, without running
+/// anything.
+fn plan(context: &RunContext, config: &Config, selection: &Selection) -> Plan {
+    let mut skipped = Vec::new();
+    let scripts_directory = match ScriptsDirectory::read() {
+        Ok(directory) => Some(directory),
+        Err(ScriptsDirectoryError::Missing) => None,
+        Err(ScriptsDirectoryError::Unreadable(path)) => {
+            skipped.push(Skipped {
+                name: format!("'{}'", path.to_string_lossy()),
+                reason: "the scripts directory could not be read".to_string(),
+            });
+            None
+        }
+    };
+
+    let default_on_failure = if config.abort_on_failure {
+        OnFailure::Abort
+    } else {
+        OnFailure::Continue
     };
+    let scripts_directory_path = scripts_directory
+        .as_ref()
+        .map(|directory| directory.path.clone());
+    let mut skip = |name: String, reason: String| skipped.push(Skipped { name, reason });
 
-    let mut executions: Vec<ScriptExecution> = Vec::new();
-    for iteration_result in scripts_directory {
+    let mut stages: BTreeMap<Stage, Vec<Script>> = BTreeMap::new();
+    for iteration_result in scripts_directory.into_iter().flatten() {
         let entry = match iteration_result {
             Ok(entry) => entry,
             Err(error) => {
-                if verbose {
-                    println!("{error:?}");
-                }
+                let Skipped { name, reason } = error.into();
+                skip(name, reason);
                 continue;
             }
         };
 
+        let path = format!("'{}'", entry.target.to_string_lossy());
         match entry.kind {
             ScriptsDirectoryEntryKind::Directory => {
-                if verbose {
-                    println!(
-                        "Skipping directory at '{}'...",
-                        entry.target.to_string_lossy()
-                    );
-                }
-                continue;
+                skip(path, "it is a directory".to_string());
             }
             ScriptsDirectoryEntryKind::NonExecutableFile => {
-                if verbose {
-                    println!(
-                        "Skipping non-executable file at '{}'...",
-                        entry.target.to_string_lossy()
-                    );
-                }
-                continue;
+                skip(path, "it is not executable".to_string());
             }
             ScriptsDirectoryEntryKind::Script => {
-                if verbose {
-                    println!("🚀 Executing '{}'...", entry.target.to_string_lossy());
+                let name = entry.script.file_name().unwrap_or_default();
+                let name = name.to_string_lossy();
+                if let Some(reason) = selection.skip_reason(&name) {
+                    skip(path, reason.to_string());
+                    continue;
                 }
 
-                let path = entry.target;
-                let mut command = Command::new(&path);
-                command.env("DMD_COLOR_MODE", mode.to_string());
+                let script_config = config.script(&name);
+                let metadata = &entry.metadata;
+                if !metadata.runs_for(context.appearance.mode) {
+                    let mode = context.appearance.mode;
+                    skip(path, format!("it does not run in {mode} mode"));
+                    continue;
+                }
 
-                if pipe_stdio {
-                    command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+                let on_accent_color_change =
+                    script_config.on_accent_color_change || metadata.on_accent_color_change;
+                if context.accent_color_only && !on_accent_color_change {
+                    skip(path, "it did not opt into accent color changes".to_string());
+                    continue;
                 }
 
-                let child = match command.spawn() {
-                    Ok(child) => child,
-                    Err(error) => {
-                        println!("❌ Failed to launch '{}': {error}", path.to_string_lossy());
-                        continue;
-                    }
-                };
-
-                executions.push(ScriptExecution {
-                    script: path,
-                    process: child,
-                });
+                let stage = entry.stage();
+                let script = Script::from_entry(entry, config, default_on_failure);
+                stages.entry(stage).or_default().push(script);
             }
         }
     }
 
-    for mut execution in executions {
-        let path = execution.script;
-        let status = match execution.process.wait() {
-            Ok(status) => status,
-            Err(error) => {
-                println!("❌ Script '{}' failed: {error}", path.to_string_lossy());
+    // Hooks never opted into accent color changes.
+    if !context.accent_color_only {
+        for hook in &config.hooks {
+            if hook.event != HookEvent::Run {
                 continue;
             }
-        };
 
-        if !status.success() {
-            println!("❌ Script '{}' failed!", path.to_string_lossy());
-        } else {
-            println!("✅ Script '{}' succeeded!", path.to_string_lossy());
+            let name = format!("hook '{}'", hook.name());
+            if let Some(reason) = selection.skip_reason(&hook.name()) {
+                skip(name, reason.to_string());
+                continue;
+            }
+
+            if !hook.runs_for(context.appearance.mode) {
+                let mode = context.appearance.mode;
+                skip(name, format!("it does not run in {mode} mode"));
+                continue;
+            }
+
+            let stage = hook.stage.map_or(Stage::Unnumbered, Stage::Numbered);
+            let script = Script::from_hook(hook, default_on_failure, config.retry);
+            stages.entry(stage).or_default().push(script);
+        }
+    }
+
+    Plan {
+        scripts_directory: scripts_directory_path,
+        stages,
+        skipped,
+    }
+}
+
+pub fn run(
+    context: RunContext,
+    config: &Config,
+    selection: &Selection,
+    verbose: bool,
+    pipe_stdio: bool,
+) -> RunReport {
+    let plan = plan(&context, config, selection);
+    if plan.scripts_directory.is_none() {
+        println!("⚠️ There is no scripts directory, only hooks are run");
+    }
+    if verbose {
+        for skipped in &plan.skipped {
+            println!("Skipping {skipped}...");
+        }
+    }
+
+    let mut report = RunReport {
+        run_id: Uuid::new_v4(),
+        results: Vec::new(),
+    };
+    let input = Input {
+        environment: context
+            .environment(report.run_id, &plan.scripts_directory.unwrap_or_default()),
+        payload: context.payload(report.run_id),
+    };
+    let mut stages = plan.stages.into_iter();
+    for (stage, scripts) in stages.by_ref() {
+        if verbose {
+            println!("Running stage {stage}...");
+        }
+
+        let should_abort = run_stage(scripts, &input, config, verbose, pipe_stdio, &mut report);
+        if should_abort {
+            println!("❌ Skipping the remaining scripts, since a script of stage {stage} failed");
+            break;
+        }
+    }
+
+    for (_, scripts) in stages {
+        for script in scripts {
+            report.results.push(script.result(ScriptOutcome::Skipped));
+        }
+    }
+
+    if !report.results.is_empty() {
+        println!("😈 {report}");
+    }
+
+    report
+}
+
+/// Runs the scripts in parallel, as far as the configured concurrency allows,
+/// and returns whether a failed script asks to skip the remaining stages.
+fn run_stage(
+    scripts: Vec<Script>,
+    input: &Input,
+    config: &Config,
+    verbose: bool,
+    pipe_stdio: bool,
+    report: &mut RunReport,
+) -> bool {
+    let max_concurrency = config.max_concurrency();
+    let mut should_abort = false;
+    let mut queue = VecDeque::from(scripts);
+    let mut running: Vec<ScriptExecution> = Vec::new();
+    let mut retrying: Vec<(Instant, Script)> = Vec::new();
+
+    while !queue.is_empty() || !running.is_empty() || !retrying.is_empty() {
+        let now = Instant::now();
+        let (due, waiting) = retrying.into_iter().partition(|(at, _)| *at <= now);
+        retrying = waiting;
+        queue.extend(due.into_iter().map(|(_, script): (Instant, Script)| script));
+
+        while let Some(next) = queue.front() {
+            let is_blocked = if next.exclusive {
+                !running.is_empty()
+            } else {
+                running.len() >= max_concurrency
+                    || running.iter().any(|execution| execution.script.exclusive)
+            };
+            if is_blocked {
+                break;
+            }
+
+            let Some(mut script) = queue.pop_front() else {
+                break;
+            };
+            let started_at = Utc::now();
+            let Some(process) = spawn(&script, input, verbose, pipe_stdio) else {
+                script.attempts.push(Attempt {
+                    started_at,
+                    duration_ms: 0,
+                    outcome: ScriptOutcome::Failed,
+                    exit_code: None,
+                });
+                finish(
+                    script,
+                    ScriptOutcome::Failed,
+                    &mut retrying,
+                    report,
+                    &mut should_abort,
+                );
+                continue;
+            };
+
+            running.push(ScriptExecution {
+                script,
+                process,
+                started: Instant::now(),
+                started_at,
+            });
         }
+
+        match poll_finished(&mut running) {
+            Some((script, outcome)) => {
+                finish(script, outcome, &mut retrying, report, &mut should_abort)
+            }
+            None => sleep(POLL_INTERVAL),
+        }
+    }
+
+    should_abort
+}
+
+/// Either schedules a retry for a failed script, or adds it to the report.
+fn finish(
+    script: Script,
+    outcome: ScriptOutcome,
+    retrying: &mut Vec<(Instant, Script)>,
+    report: &mut RunReport,
+    should_abort: &mut bool,
+) {
+    if outcome != ScriptOutcome::Succeeded {
+        if let Some(delay) = script.retry_delay() {
+            println!(
+                "🔁 Retrying {script} in {}...",
+                humantime::format_duration(delay)
+            );
+            retrying.push((Instant::now() + delay, script));
+            return;
+        }
+
+        *should_abort |= script.on_failure == OnFailure::Abort;
+    }
+
+    report.results.push(script.result(outcome));
+}
+
+fn spawn(script: &Script, input: &Input, verbose: bool, pipe_stdio: bool) -> Option<Child> {
+    if verbose {
+        println!("🚀 Executing {script}...");
     }
+
+    let mut command = Command::new(&script.program);
+    command.args(&script.args);
+    command.envs(input.environment.iter().cloned());
+    command.envs(script.env.iter().map(|(key, value)| (key, value)));
+    if let Some(cwd) = &script.cwd {
+        command.current_dir(cwd);
+    }
+
+    if pipe_stdio {
+        command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+    }
+
+    if script.stdin == ScriptInput::Json {
+        command.stdin(Stdio::piped());
+    }
+
+    match command.spawn() {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // Scripts are free to not read their input, so we ignore
+                // errors here. Dropping stdin closes it.
+                let _ = writeln!(stdin, "{}", input.payload);
+            }
+
+            Some(child)
+        }
+        Err(error) => {
+            println!("❌ Failed to launch {script}: {error}");
+            None
+        }
+    }
+}
+
+/// Returns one of the scripts that finished or timed out, along with how it
+/// went, if there is any.
+fn poll_finished(running: &mut Vec<ScriptExecution>) -> Option<(Script, ScriptOutcome)> {
+    for index in 0..running.len() {
+        let execution = &mut running[index];
+        let timed_out = execution
+            .script
+            .timeout
+            .filter(|timeout| execution.started.elapsed() >= *timeout);
+
+        let (outcome, exit_code) = if let Some(timeout) = timed_out {
+            let _ = execution.process.kill();
+            let _ = execution.process.wait();
+            println!(
+                "❌ {} timed out after {}!",
+                execution.script,
+                humantime::format_duration(timeout)
+            );
+            (ScriptOutcome::TimedOut, None)
+        } else {
+            match execution.process.try_wait() {
+                Ok(None) => continue,
+                Err(error) => {
+                    println!("❌ {} failed: {error}", execution.script);
+                    (ScriptOutcome::Failed, None)
+                }
+                Ok(Some(status)) if !status.success() => {
+                    println!("❌ {} failed!", execution.script);
+                    (ScriptOutcome::Failed, status.code())
+                }
+                Ok(Some(status)) => {
+                    println!("✅ {} succeeded!", execution.script);
+                    (ScriptOutcome::Succeeded, status.code())
+                }
+            }
+        };
+
+        let mut execution = running.swap_remove(index);
+        execution.script.attempts.push(Attempt {
+            started_at: execution.started_at,
+            duration_ms: execution.started.elapsed().as_millis(),
+            outcome,
+            exit_code,
+        });
+        return Some((execution.script, outcome));
+    }
+
+    None
 }
@@ -0,0 +1,231 @@
+use std::io::{self, stdout, Write};
+
+use clap::ValueEnum;
+use uuid::Uuid;
+
+use super::{plan, Plan, RunContext, Script, ScriptKind, Selection};
+use crate::config::{Config, ScriptInput};
+
+/// Prints what a run would execute, in which order, with which environment
+/// and settings, and what it would skip, without running anything.
+pub fn dry_run(context: RunContext, config: &Config, selection: &Selection) {
+    let plan = plan(&context, config, selection);
+    let _ = print(&mut stdout(), &context, config, &plan, Uuid::new_v4());
+}
+
+fn print(
+    out: &mut impl Write,
+    context: &RunContext,
+    config: &Config,
+    plan: &Plan,
+    run_id: Uuid,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "😈 Dry run for {} mode, nothing is executed\n",
+        context.appearance.mode
+    )?;
+    if plan.scripts_directory.is_none() {
+        writeln!(
+            out,
+            "⚠️ There is no scripts directory, only hooks would run\n"
+        )?;
+    }
+
+    writeln!(out, "Environment:")?;
+    let scripts_directory = plan.scripts_directory.clone().unwrap_or_default();
+    for (name, value) in context.environment(run_id, &scripts_directory) {
+        writeln!(out, "  {name}={value}")?;
+    }
+
+    let max_concurrency = config.max_concurrency();
+    for (stage, scripts) in &plan.stages {
+        writeln!(out, "\nStage {stage}, up to {max_concurrency} at a time:")?;
+        for script in scripts {
+            writeln!(out, "  {script}")?;
+            if script.kind == ScriptKind::Hook {
+                writeln!(out, "    $ {}", command(script))?;
+            }
+
+            let settings = settings(script);
+            if !settings.is_empty() {
+                writeln!(out, "    🏷️ {}", settings.join(" "))?;
+            }
+        }
+    }
+
+    if plan.stages.is_empty() {
+        writeln!(out, "\nNothing would run.")?;
+    }
+
+    if !plan.skipped.is_empty() {
+        writeln!(out, "\nSkipped:")?;
+        for skipped in &plan.skipped {
+            writeln!(out, "  {skipped}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The command line of a hook, quoting arguments with spaces.
+fn command(script: &Script) -> String {
+    let program = script.program.to_string_lossy().to_string();
+    let arguments = script.args.iter().map(|argument| {
+        if argument.contains(char::is_whitespace) {
+            format!("'{argument}'")
+        } else {
+            argument.clone()
+        }
+    });
+
+    [program]
+        .into_iter()
+        .chain(arguments)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Everything that affects how the script runs, in the style of the metadata
+/// of scripts.
+fn settings(script: &Script) -> Vec<String> {
+    let mut settings = Vec::new();
+    if let Some(timeout) = script.timeout {
+        settings.push(format!("timeout={}", humantime::format_duration(timeout)));
+    }
+    settings.push(format!("on-failure={}", name(script.on_failure)));
+    if script.exclusive {
+        settings.push("exclusive".to_string());
+    }
+    if script.retry.attempts.get() > 1 {
+        settings.push(format!("attempts={}", script.retry.attempts));
+        settings.push(format!(
+            "retry-delay={}",
+            humantime::format_duration(script.retry.delay)
+        ));
+        settings.push(format!("backoff={}", name(script.retry.backoff)));
+    }
+    if script.stdin == ScriptInput::Json {
+        settings.push(format!("stdin={}", name(script.stdin)));
+    }
+    if let Some(cwd) = &script.cwd {
+        settings.push(format!("cwd={}", cwd.to_string_lossy()));
+    }
+    for (name, value) in &script.env {
+        settings.push(format!("{name}={value}"));
+    }
+
+    settings
+}
+
+/// How the value is spelled on the command line and in the config.
+fn name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, num::NonZeroUsize, path::PathBuf, time::Duration};
+
+    use super::*;
+    use crate::{
+        cli::commands::{run::Skipped, Trigger},
+        config::{HookConfig, RetryConfig},
+        discovery::{metadata::OnFailure, Stage},
+        mode::{Appearance, ColorMode},
+    };
+
+    fn hook(toml: &str) -> Script {
+        let hook: HookConfig = toml::from_str(toml).unwrap();
+        Script::from_hook(&hook, OnFailure::Continue, Default::default())
+    }
+
+    fn print_plan(plan: &Plan) -> String {
+        let context = RunContext::new(Appearance::from(ColorMode::Dark), Trigger::Manual);
+        let config = Config {
+            max_concurrency: NonZeroUsize::new(2),
+            ..Config::default()
+        };
+
+        let mut out = Vec::new();
+        print(&mut out, &context, &config, plan, Uuid::nil()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn prints_the_plan() {
+        let script = Script {
+            kind: ScriptKind::Script,
+            name: "/scripts/10-kitty.sh".to_string(),
+            program: PathBuf::from("/scripts/10-kitty.sh"),
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            exclusive: true,
+            timeout: Some(Duration::from_secs(5)),
+            on_failure: OnFailure::Notify,
+            stdin: ScriptInput::default(),
+            retry: RetryConfig::default(),
+            attempts: Vec::new(),
+        };
+        let hook = hook(
+            r#"
+            name = "reload waybar"
+            command = "pkill"
+            args = ["-USR2", "waybar bar"]
+            "#,
+        );
+        let plan = Plan {
+            scripts_directory: Some(PathBuf::from("/scripts")),
+            stages: BTreeMap::from([
+                (Stage::Numbered(10), vec![script]),
+                (Stage::Unnumbered, vec![hook]),
+            ]),
+            skipped: vec![Skipped {
+                name: "'/scripts/20-gtk.sh'".to_string(),
+                reason: "it does not run in dark mode".to_string(),
+            }],
+        };
+
+        let output = print_plan(&plan);
+
+        let stages = output.split_once("\nStage").unwrap().1;
+        assert_eq!(
+            stages,
+            " 10, up to 2 at a time:
+  Script '/scripts/10-kitty.sh'
+    🏷️ timeout=5s on-failure=notify exclusive
+
+Stage without a number, up to 2 at a time:
+  Hook 'reload waybar'
+    $ pkill -USR2 'waybar bar'
+    🏷️ on-failure=continue
+
+Skipped:
+  '/scripts/20-gtk.sh', it does not run in dark mode
+"
+        );
+        assert!(output.contains("  DMD_SCRIPTS_DIR=/scripts\n"), "{output}");
+        assert!(output.contains(&format!("  DMD_RUN_ID={}\n", Uuid::nil())));
+    }
+
+    #[test]
+    fn prints_that_nothing_would_run() {
+        let plan = Plan {
+            scripts_directory: None,
+            stages: BTreeMap::new(),
+            skipped: Vec::new(),
+        };
+
+        let output = print_plan(&plan);
+
+        assert!(output.starts_with(
+            "😈 Dry run for dark mode, nothing is executed\n\n\
+             ⚠️ There is no scripts directory, only hooks would run\n"
+        ));
+        assert!(output.ends_with("\nNothing would run.\n"), "{output}");
+    }
+}
@@ -0,0 +1,259 @@
+use std::path::Path;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::{run_stage, Document, Input, RunContext, RunReport, Script, ScriptOutcome};
+use crate::{
+    config::{Config, HookEvent},
+    discovery::{metadata::OnFailure, ScriptsDirectory, ScriptsDirectoryEntryKind},
+};
+
+/// What lifecycle hooks receive on stdin if they ask for it.
+#[derive(Serialize)]
+struct Payload<'a> {
+    hook: HookEvent,
+
+    #[serde(flatten)]
+    context: Document,
+
+    /// The run that just completed, for `on-success` and `on-failure`.
+    report: Option<&'a RunReport>,
+}
+
+/// Runs the hooks for the `event`, which are the executables in
+/// `hooks/<event>/` and the `[[hooks]]` of the config with that `event`.
+///
+/// All of them run in parallel, like a single stage of scripts.
+pub fn run_lifecycle_hooks(
+    event: HookEvent,
+    context: &RunContext,
+    report: Option<&RunReport>,
+    config: &Config,
+    verbose: bool,
+) {
+    let mode = context.appearance.mode;
+    let mut scripts = Vec::new();
+    if let Ok(directory) = ScriptsDirectory::read_hooks(event) {
+        for entry in directory.flatten() {
+            let is_script = matches!(entry.kind, ScriptsDirectoryEntryKind::Script);
+            if is_script && entry.metadata.runs_for(mode) {
+                scripts.push(Script::from_entry(entry, config, OnFailure::Continue));
+            }
+        }
+    }
+
+    for hook in &config.hooks {
+        if hook.event == event && hook.runs_for(mode) {
+            scripts.push(Script::from_hook(hook, OnFailure::Continue, config.retry));
+        }
+    }
+
+    if scripts.is_empty() {
+        return;
+    }
+
+    let run_id = report.map_or_else(Uuid::new_v4, |report| report.run_id);
+    let scripts_directory = ScriptsDirectory::read()
+        .map(|directory| directory.path)
+        .unwrap_or_default();
+    let input = input(event, context, report, run_id, &scripts_directory);
+
+    println!("🪝 Running {event} hooks...");
+    let mut hooks_report = RunReport {
+        run_id,
+        results: Vec::new(),
+    };
+    run_stage(scripts, &input, config, verbose, true, &mut hooks_report);
+    println!("🪝 {hooks_report}");
+}
+
+/// Runs the `on-success` or `on-failure` hooks, depending on how the scripts
+/// of the `report` went.
+pub fn run_completion_hooks(
+    context: &RunContext,
+    report: &RunReport,
+    config: &Config,
+    verbose: bool,
+) {
+    let event = completion_event(report);
+    run_lifecycle_hooks(event, context, Some(report), config, verbose);
+}
+
+/// Which hooks run after the scripts of the `report`.
+fn completion_event(report: &RunReport) -> HookEvent {
+    if report.failed() {
+        HookEvent::OnFailure
+    } else {
+        HookEvent::OnSuccess
+    }
+}
+
+/// What the hooks for the `event` receive in their environment and on stdin.
+fn input(
+    event: HookEvent,
+    context: &RunContext,
+    report: Option<&RunReport>,
+    run_id: Uuid,
+    scripts_directory: &Path,
+) -> Input {
+    let mut environment = context.environment(run_id, scripts_directory);
+    environment.push(("DMD_HOOK", event.to_string()));
+    if let Some(report) = report {
+        let failed = report.count(ScriptOutcome::Failed) + report.count(ScriptOutcome::TimedOut);
+        environment.extend([
+            ("DMD_RUN_SUMMARY", report.to_string()),
+            (
+                "DMD_RUN_SUCCEEDED",
+                report.count(ScriptOutcome::Succeeded).to_string(),
+            ),
+            ("DMD_RUN_FAILED", failed.to_string()),
+            (
+                "DMD_RUN_SKIPPED",
+                report.count(ScriptOutcome::Skipped).to_string(),
+            ),
+        ]);
+    }
+
+    let payload = Payload {
+        hook: event,
+        context: context.document(run_id),
+        report,
+    };
+
+    Input {
+        environment,
+        payload: serde_json::to_string(&payload).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        cli::commands::{ScriptKind, ScriptResult, Trigger},
+        mode::{Appearance, ColorMode},
+    };
+
+    fn report(outcomes: &[ScriptOutcome]) -> RunReport {
+        let results = outcomes
+            .iter()
+            .map(|outcome| ScriptResult {
+                kind: ScriptKind::Script,
+                name: "10-kitty.sh".to_string(),
+                outcome: *outcome,
+                attempts: Vec::new(),
+                notify: false,
+            })
+            .collect();
+
+        RunReport {
+            run_id: Uuid::nil(),
+            results,
+        }
+    }
+
+    fn context() -> RunContext {
+        RunContext::new(Appearance::from(ColorMode::Dark), Trigger::Change)
+    }
+
+    fn variable(input: &Input, name: &str) -> Option<String> {
+        input
+            .environment
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    #[test]
+    fn picks_hooks_by_outcome() {
+        use ScriptOutcome::*;
+
+        assert_eq!(completion_event(&report(&[])), HookEvent::OnSuccess);
+        let succeeded = report(&[Succeeded, Skipped]);
+        assert_eq!(completion_event(&succeeded), HookEvent::OnSuccess);
+        let failed = report(&[Succeeded, Failed]);
+        assert_eq!(completion_event(&failed), HookEvent::OnFailure);
+        let timed_out = report(&[TimedOut]);
+        assert_eq!(completion_event(&timed_out), HookEvent::OnFailure);
+    }
+
+    #[test]
+    fn describes_the_run_in_the_environment() {
+        use ScriptOutcome::*;
+        let report = report(&[Succeeded, Succeeded, Failed, TimedOut, Skipped]);
+
+        let input = input(
+            HookEvent::OnFailure,
+            &context(),
+            Some(&report),
+            report.run_id,
+            Path::new("/scripts"),
+        );
+
+        assert_eq!(variable(&input, "DMD_HOOK").unwrap(), "on-failure");
+        assert_eq!(
+            variable(&input, "DMD_RUN_SUMMARY").unwrap(),
+            report.to_string()
+        );
+        assert_eq!(variable(&input, "DMD_RUN_SUCCEEDED").unwrap(), "2");
+        assert_eq!(variable(&input, "DMD_RUN_FAILED").unwrap(), "2");
+        assert_eq!(variable(&input, "DMD_RUN_SKIPPED").unwrap(), "1");
+        assert_eq!(
+            variable(&input, "DMD_RUN_ID").unwrap(),
+            Uuid::nil().to_string()
+        );
+        assert_eq!(variable(&input, "DMD_COLOR_MODE").unwrap(), "dark");
+    }
+
+    #[test]
+    fn leaves_out_the_run_without_a_report() {
+        let input = input(
+            HookEvent::OnStart,
+            &context(),
+            None,
+            Uuid::nil(),
+            Path::new("/scripts"),
+        );
+
+        assert_eq!(variable(&input, "DMD_HOOK").unwrap(), "on-start");
+        assert_eq!(variable(&input, "DMD_RUN_SUMMARY"), None);
+
+        let payload: Value = serde_json::from_str(&input.payload).unwrap();
+        assert_eq!(payload["report"], Value::Null);
+    }
+
+    #[test]
+    fn sends_the_context_and_report_as_payload() {
+        let report = report(&[ScriptOutcome::Failed]);
+
+        let input = input(
+            HookEvent::OnFailure,
+            &context(),
+            Some(&report),
+            report.run_id,
+            Path::new("/scripts"),
+        );
+
+        let payload: Value = serde_json::from_str(&input.payload).unwrap();
+        assert_eq!(payload["hook"], "on-failure");
+        assert_eq!(payload["run_id"], Uuid::nil().to_string());
+        assert_eq!(payload["trigger"], "change");
+        assert_eq!(payload["mode"], "dark");
+        assert_eq!(
+            payload["report"],
+            json!({
+                "run_id": Uuid::nil(),
+                "results": [{
+                    "kind": "script",
+                    "name": "10-kitty.sh",
+                    "outcome": "failed",
+                    "attempts": [],
+                    "notify": false,
+                }],
+            })
+        );
+    }
+}
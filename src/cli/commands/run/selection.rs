@@ -0,0 +1,96 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Which scripts and hooks of a run are executed, chosen by matching the file
+/// names of scripts and the names of hooks against globs.
+#[derive(Default)]
+pub struct Selection {
+    /// Everything that does not match is skipped, if set.
+    only: Option<GlobSet>,
+
+    exclude: GlobSet,
+}
+
+impl Selection {
+    pub fn new(only: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            only: if only.is_empty() {
+                None
+            } else {
+                Some(glob_set(only)?)
+            },
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    /// Why the script or hook with the given name is not selected, unless it
+    /// is.
+    pub(super) fn skip_reason(&self, name: &str) -> Option<&'static str> {
+        if self.only.as_ref().is_some_and(|only| !only.is_match(name)) {
+            return Some("it does not match --only");
+        }
+
+        if self.exclude.is_match(name) {
+            return Some("it matches --exclude");
+        }
+
+        None
+    }
+}
+
+fn glob_set(globs: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection(only: &[&str], exclude: &[&str]) -> Selection {
+        let only: Vec<_> = only.iter().map(ToString::to_string).collect();
+        let exclude: Vec<_> = exclude.iter().map(ToString::to_string).collect();
+        Selection::new(&only, &exclude).unwrap()
+    }
+
+    #[test]
+    fn selects_everything_by_default() {
+        assert_eq!(Selection::default().skip_reason("10-kitty.sh"), None);
+    }
+
+    #[test]
+    fn only_selects_matches() {
+        let selection = selection(&["10-*", "*helix*"], &[]);
+
+        assert_eq!(selection.skip_reason("10-kitty.sh"), None);
+        assert_eq!(selection.skip_reason("reload helix"), None);
+        assert_eq!(
+            selection.skip_reason("20-gtk.sh"),
+            Some("it does not match --only")
+        );
+    }
+
+    #[test]
+    fn exclude_wins_over_only() {
+        let selection = selection(&["*.sh"], &["*kitty*"]);
+
+        assert_eq!(selection.skip_reason("20-gtk.sh"), None);
+        assert_eq!(
+            selection.skip_reason("10-kitty.sh"),
+            Some("it matches --exclude")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let error = Selection::new(&["[".to_string()], &[]).err().unwrap();
+
+        assert!(
+            error.to_string().contains("error parsing glob '['"),
+            "{error}"
+        );
+    }
+}
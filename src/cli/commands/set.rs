@@ -0,0 +1,11 @@
+use crate::mode::ColorMode;
+
+pub async fn set(mode: ColorMode) -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    crate::platform::linux::appearance::set_color_mode(mode).await?;
+
+    #[cfg(target_os = "macos")]
+    crate::platform::macos::appearance::set_color_mode(mode).await?;
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anyhow::anyhow;
+
+use crate::ipc::{self, Request, Response, Status};
+
+pub async fn status() -> anyhow::Result<()> {
+    request_status(Request::Status).await
+}
+
+/// Sends a request to the daemon and prints its status afterwards.
+pub(crate) async fn request_status(request: Request) -> anyhow::Result<()> {
+    match ipc::send(request).await? {
+        Response::Status(status) => print_status(&status),
+        Response::Error { message } => return Err(anyhow!(message)),
+        response => return Err(anyhow!("Unexpected response from daemon: {response:?}")),
+    }
+
+    Ok(())
+}
+
+fn print_status(status: &Status) {
+    let detected = status.detected_mode;
+    let applied = status.applied_mode;
+    println!("Detected: {} {}", detected.emoji(), detected);
+    println!("Applied:  {} {}", applied.emoji(), applied);
+
+    if let Some(active_override) = status.active_override {
+        println!(
+            "Override: {} {} {}",
+            active_override.mode.emoji(),
+            active_override.mode,
+            active_override.expiry
+        );
+    }
+
+    if let Some(pause) = status.paused {
+        println!("Paused:   ⏸️ {pause}");
+    }
+}
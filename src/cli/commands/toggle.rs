@@ -0,0 +1,7 @@
+use crate::{cli::commands::set, platform::backend::ColorModeBackend};
+
+pub async fn toggle(backend: Box<dyn ColorModeBackend>) -> anyhow::Result<()> {
+    let mode = backend.current_appearance().await?.mode;
+
+    set(mode.other()).await
+}
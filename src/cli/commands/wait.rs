@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tokio::sync::mpsc;
+
+use crate::{
+    mode::ColorMode,
+    platform::{backend::ColorModeBackend, ColorModeEvent},
+};
+
+/// Returns once the backend reports the `mode`, or fails after the `timeout`.
+pub async fn wait(
+    backend: Box<dyn ColorModeBackend>,
+    mode: ColorMode,
+    timeout: Option<Duration>,
+) -> anyhow::Result<()> {
+    if backend.current_appearance().await?.mode == mode {
+        return Ok(());
+    }
+
+    let (matched, mut matches) = mpsc::unbounded_channel();
+    let on_event = Box::new(move |event| {
+        if let ColorModeEvent::AppearanceChanged(appearance) = event {
+            if appearance.mode == mode {
+                let _ = matched.send(());
+            }
+        }
+    });
+    // The mode may have switched right before the backend started watching,
+    // in which case no event reports it, so we look once more.
+    let recheck = async {
+        match backend.current_appearance().await {
+            Ok(appearance) if appearance.mode == mode => Ok(()),
+            _ => std::future::pending().await,
+        }
+    };
+    let watch = async {
+        tokio::select! {
+            biased;
+            _ = backend.start(on_event) => {
+                Err(anyhow!("Stopped watching the color mode before it became {mode}"))
+            }
+            _ = matches.recv() => Ok(()),
+            result = recheck => result,
+        }
+    };
+
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, watch).await.map_err(|_| {
+            anyhow!(
+                "Timed out after {} waiting for {mode} mode",
+                humantime::format_duration(timeout)
+            )
+        })?,
+        None => watch.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::backend::fake::FakeBackend;
+
+    fn backend(mode: ColorMode, changes: Vec<ColorMode>) -> Box<dyn ColorModeBackend> {
+        Box::new(FakeBackend {
+            changes,
+            ..FakeBackend::new(mode)
+        })
+    }
+
+    #[tokio::test]
+    async fn returns_right_away_if_the_mode_matches() {
+        let backend = backend(ColorMode::Dark, vec![]);
+
+        wait(backend, ColorMode::Dark, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn returns_once_the_mode_changes() {
+        let changes = vec![ColorMode::Light, ColorMode::Dark];
+        let backend = backend(ColorMode::Light, changes);
+
+        wait(backend, ColorMode::Dark, None).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn notices_changes_right_before_watching() {
+        // Switched after the first look, but before the backend started, so
+        // there is no event for it.
+        let backend = Box::new(FakeBackend::switching(vec![
+            ColorMode::Light,
+            ColorMode::Dark,
+        ]));
+
+        let timeout = Some(Duration::from_secs(30));
+        wait(backend, ColorMode::Dark, timeout).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out() {
+        let backend = backend(ColorMode::Light, vec![ColorMode::Light]);
+
+        let error = wait(backend, ColorMode::Dark, Some(Duration::from_secs(30)))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Timed out after 30s waiting for dark mode"
+        );
+    }
+}
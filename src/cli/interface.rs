@@ -1,6 +1,8 @@
+use std::{ffi::OsString, time::Duration};
+
 use clap::{Parser, Subcommand};
 
-use crate::mode::ColorMode;
+use crate::{config::DetectorKind, mode::ColorMode};
 
 /// 😈 Run scripts when the system color scheme changes between light and dark. 🦇
 #[derive(Parser)]
@@ -8,6 +10,10 @@ use crate::mode::ColorMode;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Command>,
+
+    /// Where the color mode comes from, instead of the one configured.
+    #[arg(long, global = true)]
+    pub detector: Option<DetectorKind>,
 }
 
 #[derive(Subcommand)]
@@ -31,6 +37,17 @@ pub enum Command {
         plain: bool,
     },
 
+    /// Waits until the color mode is the given one, e.g. to synchronize
+    /// shell scripts with it.
+    Wait {
+        /// The mode to wait for.
+        mode: ColorMode,
+
+        /// Give up after some time, e.g. `30s`, and exit with an error.
+        #[arg(long, value_parser = humantime::parse_duration)]
+        timeout: Option<Duration>,
+    },
+
     /// Manually run scripts for testing.
     Run {
         /// The mode that the scripts should be run for.
@@ -38,8 +55,81 @@ pub enum Command {
 
         #[arg(short, long)]
         verbose: bool,
+
+        /// Only run scripts and hooks whose file or hook name matches one of
+        /// these globs, e.g. `'10-*'`.
+        #[arg(long, value_name = "GLOB")]
+        only: Vec<String>,
+
+        /// Skip scripts and hooks whose file or hook name matches one of these
+        /// globs.
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Print which scripts would run in which order, with their
+        /// environment and settings, without running anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Runs a single command with the environment variables scripts receive,
+    /// e.g. to launch apps with the right theme or to try out a script.
+    Exec {
+        /// The command and its arguments, best passed after `--`.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<OsString>,
     },
 
+    /// Temporarily runs the scripts for a different mode than the one of the
+    /// operating system. Requires a running daemon.
+    Override {
+        /// The mode the scripts should be run for.
+        #[arg(required_unless_present = "clear")]
+        mode: Option<ColorMode>,
+
+        /// How long the override lasts, e.g. `2h` or `30m`. Without this, the
+        /// override lasts until the operating system changes its mode.
+        #[arg(long = "for", value_parser = humantime::parse_duration, conflicts_with = "until_next_change")]
+        duration: Option<Duration>,
+
+        /// End the override once the operating system changes its mode.
+        #[arg(long)]
+        until_next_change: bool,
+
+        /// End an active override right away.
+        #[arg(long, conflicts_with_all = ["mode", "duration", "until_next_change"])]
+        clear: bool,
+    },
+
+    /// Stops the running daemon from running scripts, without stopping it.
+    Pause {
+        /// Resume automatically after some time, e.g. `30m`.
+        #[arg(long = "for", value_parser = humantime::parse_duration)]
+        duration: Option<Duration>,
+    },
+
+    /// Lets a paused daemon run scripts again.
+    Resume {
+        /// Don't run the scripts, even if the color mode changed while paused.
+        #[arg(long)]
+        no_run: bool,
+    },
+
+    /// Prints what the running daemon is currently doing.
+    Status,
+
+    /// Prints the color mode changes the running daemon has seen.
+    History,
+
+    /// Changes the color scheme of the operating system.
+    Set {
+        /// The mode to switch to.
+        mode: ColorMode,
+    },
+
+    /// Switches the operating system between light and dark mode.
+    Toggle,
+
     /// Prints the scripts that would be run.
     List {
         /// Print resolved target locations for symlinked scripts.
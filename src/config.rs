@@ -0,0 +1,556 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    num::{NonZeroU32, NonZeroUsize},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use xdg::BaseDirectories;
+
+use crate::mode::ColorMode;
+
+/// Long enough to catch duplicate events, short enough to not be noticeable.
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Settings read from `~/.config/dark-mode-daemon/config.toml`.
+///
+/// Every key is optional, so a missing file behaves like an empty one.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Where the color mode comes from.
+    pub detector: DetectorKind,
+
+    /// Settings for the `schedule` detector.
+    pub schedule: Option<ScheduleConfig>,
+
+    /// How long changes need to settle before the scripts run, e.g. `"250ms"`.
+    #[serde(deserialize_with = "duration")]
+    #[schemars(with = "String")]
+    pub debounce: Duration,
+
+    /// Skip the remaining stages once a script failed.
+    pub abort_on_failure: bool,
+
+    /// How many scripts may run at the same time. Defaults to the number of
+    /// CPUs.
+    pub max_concurrency: Option<NonZeroUsize>,
+
+    /// Settings for individual scripts, keyed by their file name.
+    pub scripts: HashMap<String, ScriptConfig>,
+
+    /// Commands that run alongside the scripts.
+    pub hooks: Vec<HookConfig>,
+
+    /// How failing scripts are retried, unless they configure it themselves.
+    pub retry: RetryConfig,
+
+    /// Desktop notifications about failed scripts.
+    pub notifications: NotificationsConfig,
+
+    /// HTTP endpoints that receive a JSON document for every change.
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// An MQTT broker that is told about every change.
+    pub mqtt: Option<MqttConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            detector: DetectorKind::default(),
+            schedule: None,
+            debounce: DEFAULT_DEBOUNCE,
+            abort_on_failure: false,
+            max_concurrency: None,
+            scripts: HashMap::new(),
+            hooks: Vec::new(),
+            retry: RetryConfig::default(),
+            notifications: NotificationsConfig::default(),
+            webhooks: Vec::new(),
+            mqtt: None,
+        }
+    }
+}
+
+#[derive(Default, Clone, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// Also run the script when only the accent color changes.
+    pub on_accent_color_change: bool,
+
+    /// Never run the script at the same time as any other script.
+    pub exclusive: bool,
+
+    /// What the script receives on stdin.
+    pub stdin: Option<ScriptInput>,
+
+    /// How the script is retried when it fails.
+    pub retry: Option<RetryConfig>,
+}
+
+/// How often and when failing scripts are run again.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RetryConfig {
+    /// How often a script is run at most, including the first attempt.
+    pub attempts: NonZeroU32,
+
+    /// How long to wait before the first retry, e.g. `"1s"`.
+    #[serde(deserialize_with = "duration")]
+    #[schemars(with = "String")]
+    pub delay: Duration,
+
+    pub backoff: Backoff,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: NonZeroU32::MIN,
+            delay: Duration::from_secs(1),
+            backoff: Backoff::Fixed,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// How long to wait after the given (1-based) attempt failed.
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed => self.delay,
+            Backoff::Exponential => self
+                .delay
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))),
+        }
+    }
+}
+
+/// How the delay between retries grows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backoff {
+    /// Always wait for the same time.
+    #[default]
+    Fixed,
+
+    /// Double the delay after every attempt.
+    Exponential,
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    humantime::parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+/// When a hook runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// Alongside the scripts, whenever they run.
+    #[default]
+    Run,
+
+    /// When the daemon starts, before it runs the scripts for the first time.
+    OnStart,
+
+    /// When the daemon shuts down.
+    OnStop,
+
+    /// After the daemon ran the scripts and all of them succeeded.
+    OnSuccess,
+
+    /// After the daemon ran the scripts and some of them failed.
+    OnFailure,
+}
+
+impl Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let event = match self {
+            HookEvent::Run => "run",
+            HookEvent::OnStart => "on-start",
+            HookEvent::OnStop => "on-stop",
+            HookEvent::OnSuccess => "on-success",
+            HookEvent::OnFailure => "on-failure",
+        };
+
+        write!(f, "{event}")
+    }
+}
+
+/// An HTTP endpoint that is sent a `POST` request whenever the daemon ran
+/// the scripts.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    /// Shown in the output instead of the URL, which might contain secrets.
+    pub name: Option<String>,
+
+    /// How long a single request may take, e.g. `"5s"`.
+    #[serde(
+        default = "WebhookConfig::default_timeout",
+        deserialize_with = "duration"
+    )]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+
+    /// How failed requests are retried.
+    #[serde(default = "WebhookConfig::default_retry")]
+    pub retry: RetryConfig,
+
+    /// Additional HTTP headers, e.g. for authentication.
+    #[serde(default)]
+    pub headers: HashMap<String, SecretValue>,
+
+    /// Also send a request when only the accent color changed.
+    #[serde(default)]
+    pub on_accent_color_change: bool,
+}
+
+impl WebhookConfig {
+    fn default_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Networks are flaky, so webhooks are retried by default.
+    fn default_retry() -> RetryConfig {
+        RetryConfig {
+            attempts: NonZeroU32::new(3).unwrap_or(NonZeroU32::MIN),
+            ..RetryConfig::default()
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.url)
+    }
+}
+
+/// Where the daemon publishes the applied mode over MQTT.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MqttConfig {
+    /// The host name or IP address of the broker.
+    pub host: String,
+
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+
+    /// Messages are published below this topic, e.g. to `<topic>/mode`.
+    #[serde(default = "MqttConfig::default_topic")]
+    pub topic: String,
+
+    /// Needs to be unique per broker, so set this when running the daemon on
+    /// multiple machines.
+    #[serde(default = "MqttConfig::default_topic")]
+    pub client_id: String,
+
+    pub username: Option<String>,
+
+    pub password: Option<SecretValue>,
+
+    /// Also publish an event when only the accent color changed.
+    #[serde(default)]
+    pub on_accent_color_change: bool,
+}
+
+impl MqttConfig {
+    fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_topic() -> String {
+        "dark-mode-daemon".to_string()
+    }
+}
+
+/// A value that might be a secret, like an HTTP header or a password.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(
+    untagged,
+    expecting = "a string, or a table with either an `env` or a `file`, and an optional `prefix`"
+)]
+pub enum SecretValue {
+    /// Used as is.
+    Plain(String),
+
+    /// Read from an environment variable or a file every time it is used, so
+    /// secrets don't need to be part of the config.
+    Secret(SecretSource),
+}
+
+/// Where a secret is read from. Exactly one of `env` and `file` is required.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum SecretSource {
+    Env {
+        /// The environment variable containing the value.
+        env: String,
+
+        /// Put in front of the value, e.g. `"Bearer "`.
+        #[serde(default)]
+        prefix: String,
+    },
+    File {
+        /// The file containing the value. Surrounding whitespace is removed.
+        file: PathBuf,
+
+        /// Put in front of the value, e.g. `"Bearer "`.
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+impl SecretValue {
+    /// The value to send.
+    pub fn resolve(&self) -> anyhow::Result<String> {
+        match self {
+            SecretValue::Plain(value) => Ok(value.clone()),
+            SecretValue::Secret(SecretSource::Env { env, prefix }) => {
+                let value = std::env::var(env)
+                    .with_context(|| format!("Could not read environment variable '{env}'"))?;
+                Ok(format!("{prefix}{value}"))
+            }
+            SecretValue::Secret(SecretSource::File { file, prefix }) => {
+                let value = fs::read_to_string(file)
+                    .with_context(|| format!("Could not read '{}'", file.display()))?;
+                Ok(format!("{prefix}{}", value.trim()))
+            }
+        }
+    }
+}
+
+/// When the daemon shows desktop notifications.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Notify about every failed script, not only about those with
+    /// `on-failure=notify`.
+    pub on_failure: bool,
+
+    /// How long to wait at least between two notifications, e.g. `"5m"`.
+    /// Failures in between are summarized in the next one.
+    #[serde(deserialize_with = "duration")]
+    #[schemars(with = "String")]
+    pub min_interval: Duration,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            on_failure: false,
+            min_interval: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// What a script receives on stdin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, JsonSchema, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptInput {
+    /// Whatever the daemon received on stdin, usually nothing.
+    #[default]
+    Inherit,
+
+    /// A JSON document describing why the script runs.
+    Json,
+}
+
+/// A command that is run like a script, without needing a file.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HookConfig {
+    /// Shown in the output instead of the command.
+    pub name: Option<String>,
+
+    /// The program to run, or a whole command line if `shell` is set.
+    pub command: String,
+
+    /// Arguments passed to the program.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Run `command` through `sh -c`, e.g. to use pipes.
+    #[serde(default)]
+    pub shell: bool,
+
+    /// Only run for these modes.
+    pub modes: Option<Vec<ColorMode>>,
+
+    /// Additional environment variables.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// The working directory.
+    pub cwd: Option<PathBuf>,
+
+    /// When the hook runs. By default, it runs alongside the scripts.
+    #[serde(default)]
+    pub event: HookEvent,
+
+    /// The stage to run in. Hooks without a stage run last.
+    pub stage: Option<u32>,
+
+    /// What the hook receives on stdin.
+    #[serde(default)]
+    pub stdin: ScriptInput,
+
+    /// How the hook is retried when it fails.
+    pub retry: Option<RetryConfig>,
+}
+
+impl HookConfig {
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => [self.command.as_str()]
+                .into_iter()
+                .chain(self.args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Whether the hook should run for the given mode.
+    pub fn runs_for(&self, mode: ColorMode) -> bool {
+        self.modes
+            .as_ref()
+            .is_none_or(|modes| modes.contains(&mode))
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DetectorKind {
+    /// Follow the color scheme of the operating system.
+    #[default]
+    Native,
+
+    /// Switch at fixed times or at sunrise and sunset.
+    Schedule,
+}
+
+/// Either fixed times of day, or a location to compute sunrise and sunset for.
+#[derive(Clone, Deserialize, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ScheduleConfig {
+    Fixed {
+        /// When light mode starts, e.g. `"07:00"`.
+        light: String,
+
+        /// When dark mode starts, e.g. `"19:30"`.
+        dark: String,
+    },
+    Sun {
+        latitude: f64,
+        longitude: f64,
+
+        /// Shifts the switch to light mode, e.g. `"30m"` or `"-1h"`.
+        #[serde(default, rename = "sunrise-offset")]
+        sunrise_offset: Option<String>,
+
+        /// Shifts the switch to dark mode, e.g. `"30m"` or `"-1h"`.
+        #[serde(default, rename = "sunset-offset")]
+        sunset_offset: Option<String>,
+    },
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let dmd_directory = BaseDirectories::with_prefix("dark-mode-daemon").ok()?;
+        dmd_directory.find_config_file("config.toml")
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read config file '{}'", path.display()))?;
+
+        // Only the outermost error is shown, so the cause goes into it.
+        toml::from_str(&contents)
+            .map_err(|error| anyhow!("Invalid config file '{}': {error}", path.display()))
+    }
+
+    /// How many scripts may run at the same time.
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
+
+    /// The settings for the script with the given file name.
+    pub fn script(&self, name: &str) -> ScriptConfig {
+        self.scripts.get(name).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(toml: &str) -> Result<SecretValue, toml::de::Error> {
+        #[derive(Deserialize)]
+        struct Header {
+            value: SecretValue,
+        }
+
+        toml::from_str::<Header>(&format!("value = {toml}")).map(|header| header.value)
+    }
+
+    #[test]
+    fn reads_the_debounce_when_loading() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.debounce, DEFAULT_DEBOUNCE);
+
+        let config: Config = toml::from_str(r#"debounce = "1s""#).unwrap();
+        assert_eq!(config.debounce, Duration::from_secs(1));
+
+        assert!(toml::from_str::<Config>(r#"debounce = "soon""#).is_err());
+    }
+
+    #[test]
+    fn reads_secrets_from_either_env_or_file() {
+        assert!(matches!(
+            secret(r#""plain""#),
+            Ok(SecretValue::Plain(value)) if value == "plain"
+        ));
+        assert!(matches!(
+            secret(r#"{ env = "TOKEN", prefix = "Bearer " }"#),
+            Ok(SecretValue::Secret(SecretSource::Env { env, prefix }))
+                if env == "TOKEN" && prefix == "Bearer "
+        ));
+        assert!(matches!(
+            secret(r#"{ file = "/run/secrets/token" }"#),
+            Ok(SecretValue::Secret(SecretSource::File { prefix, .. })) if prefix.is_empty()
+        ));
+    }
+
+    #[test]
+    fn rejects_secrets_without_exactly_one_source() {
+        for invalid in [
+            r#"{ env = "TOKEN", file = "/run/secrets/token" }"#,
+            r#"{ prefix = "Bearer " }"#,
+            "{}",
+        ] {
+            let error = secret(invalid).err().unwrap();
+            assert!(
+                error.to_string().contains("either an `env` or a `file`"),
+                "{error}"
+            );
+        }
+    }
+}
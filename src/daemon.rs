@@ -0,0 +1,376 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{mpsc::UnboundedReceiver, oneshot, watch};
+
+use crate::{
+    cli::commands::{
+        run, run_completion_hooks, run_lifecycle_hooks, RunContext, Selection, Trigger,
+    },
+    config::{Config, DetectorKind, HookEvent},
+    ipc::{
+        HistoryEntry, HistoryOutcome, Override, OverrideExpiry, Pause, Request, Response,
+        SkipReason, Status,
+    },
+    mode::{AccentColor, Appearance},
+    notifications::Notifier,
+    platform::ColorModeEvent,
+    sinks::Sinks,
+};
+
+/// The longest we wait before checking whether an override or pause expired.
+///
+/// Like for schedules, timers do not advance while the machine is suspended,
+/// so we compare against the wall clock regularly instead of sleeping once.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// How many entries of the history we keep around.
+const HISTORY_SIZE: usize = 100;
+
+/// Everything the daemon reacts to.
+pub enum Event {
+    /// The detector noticed a change.
+    Detected(ColorModeEvent),
+
+    /// The CLI sent a request over the socket.
+    Request(Request, oneshot::Sender<Response>),
+
+    /// The daemon is about to exit.
+    Shutdown,
+}
+
+/// Decides which mode the scripts should be run for.
+pub struct State {
+    config: Arc<Config>,
+    verbose: bool,
+    detector: Option<&'static str>,
+    detector_kind: DetectorKind,
+    detected: Appearance,
+    applied: Appearance,
+    accent_color: Option<AccentColor>,
+    applied_accent_color: Option<AccentColor>,
+    active_override: Option<Override>,
+    paused: Option<Pause>,
+    history: VecDeque<HistoryEntry>,
+    notifier: Notifier,
+    sinks: Sinks,
+    last_run: Option<DateTime<Utc>>,
+    status: watch::Sender<Status>,
+}
+
+impl State {
+    /// Assumes the scripts were already run for the `initial` context, which
+    /// was detected by a detector of the given kind.
+    pub fn new(
+        initial: RunContext,
+        detector_kind: DetectorKind,
+        config: Arc<Config>,
+        notifier: Notifier,
+        sinks: Sinks,
+        verbose: bool,
+    ) -> Self {
+        let status = Status {
+            detected_mode: initial.appearance.mode,
+            applied_mode: initial.appearance.mode,
+            active_override: None,
+            paused: None,
+            last_run: Some(Utc::now()),
+        };
+        let mut state = Self {
+            config,
+            verbose,
+            detector: initial.detector,
+            detector_kind,
+            detected: initial.appearance,
+            applied: initial.appearance,
+            accent_color: initial.accent_color,
+            applied_accent_color: initial.accent_color,
+            active_override: None,
+            paused: None,
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            notifier,
+            sinks,
+            last_run: status.last_run,
+            status: watch::Sender::new(status),
+        };
+        state.record(initial.appearance, HistoryOutcome::Applied);
+
+        state
+    }
+
+    /// Follows the status, which changes after every event.
+    pub fn subscribe(&self) -> watch::Receiver<Status> {
+        self.status.subscribe()
+    }
+
+    pub async fn run(mut self, mut events: UnboundedReceiver<Event>) {
+        loop {
+            let sleep_duration = self.time_until_next_deadline();
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(Event::Detected(event)) => self.detected(event).await,
+                    Some(Event::Request(request, respond)) => {
+                        let response = self.request(request).await;
+                        let _ = respond.send(response);
+                    }
+                    Some(Event::Shutdown) => return self.shutdown().await,
+                    None => return,
+                },
+                _ = tokio::time::sleep(sleep_duration.unwrap_or(MAX_SLEEP)), if sleep_duration.is_some() => {
+                    self.expire().await;
+                }
+            }
+            self.status.send_replace(self.status());
+        }
+    }
+
+    async fn detected(&mut self, event: ColorModeEvent) {
+        match event {
+            ColorModeEvent::AppearanceChanged(appearance) => self.color_changed(appearance).await,
+            ColorModeEvent::AccentColorChanged(accent_color) => {
+                self.accent_color = Some(accent_color);
+                self.reconcile(self.change_trigger()).await;
+            }
+        }
+    }
+
+    async fn shutdown(self) {
+        let context = self.context(self.applied, Trigger::Shutdown);
+        let config = self.config.clone();
+        let verbose = self.verbose;
+        let _ = tokio::task::spawn_blocking(move || {
+            run_lifecycle_hooks(HookEvent::OnStop, &context, None, &config, verbose)
+        })
+        .await;
+        self.sinks.shutdown().await;
+    }
+
+    async fn color_changed(&mut self, appearance: Appearance) {
+        let mode = appearance.mode;
+        let is_change = appearance != self.detected;
+        self.detected = appearance;
+
+        if is_change
+            && self
+                .active_override
+                .is_some_and(|active| active.expiry == OverrideExpiry::NextChange)
+        {
+            println!("😈 Color mode changed, ending override...");
+            self.active_override = None;
+        }
+
+        if self.paused.is_some() {
+            if is_change {
+                if self.verbose {
+                    println!("Ignoring change to {mode}, the daemon is paused");
+                }
+                self.record(appearance, HistoryOutcome::Skipped(SkipReason::Paused));
+            }
+            return;
+        }
+
+        match self.active_override {
+            Some(active_override) => {
+                if self.verbose {
+                    println!(
+                        "Ignoring change to {mode}, overridden to {} {}",
+                        active_override.mode, active_override.expiry
+                    );
+                }
+                self.record(appearance, HistoryOutcome::Skipped(SkipReason::Overridden));
+            }
+            None => self.apply(appearance, self.change_trigger()).await,
+        }
+    }
+
+    async fn request(&mut self, request: Request) -> Response {
+        match request {
+            Request::Status => {}
+            Request::History => {
+                return Response::History {
+                    entries: self.history.iter().cloned().collect(),
+                };
+            }
+            Request::Override(new_override) => {
+                println!(
+                    "😈 Overriding color mode to {} {}...",
+                    new_override.mode, new_override.expiry
+                );
+                self.active_override = Some(new_override);
+                self.reconcile(Trigger::Override).await;
+            }
+            Request::ClearOverride => {
+                if self.active_override.take().is_some() {
+                    println!("😈 Override cleared...");
+                    self.reconcile(Trigger::Override).await;
+                }
+            }
+            Request::Pause(pause) => {
+                println!("😈 Pausing {pause}...");
+                self.paused = Some(pause);
+            }
+            Request::Rerun => {
+                if self.paused.is_some() {
+                    return Response::Error {
+                        message: "The daemon is paused".to_string(),
+                    };
+                }
+
+                println!("😈 Running scripts again...");
+                self.apply(self.effective_appearance(), Trigger::Manual)
+                    .await;
+            }
+            Request::Resume { run_scripts } => {
+                if self.paused.take().is_some() {
+                    println!("😈 Resuming...");
+                    if run_scripts {
+                        self.reconcile(Trigger::Resume).await;
+                    }
+                }
+            }
+        }
+
+        Response::Status(self.status())
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            detected_mode: self.detected.mode,
+            applied_mode: self.applied.mode,
+            active_override: self.active_override,
+            paused: self.paused,
+            last_run: self.last_run,
+        }
+    }
+
+    /// The appearance the scripts should be run for when not paused.
+    fn effective_appearance(&self) -> Appearance {
+        match self.active_override {
+            Some(active_override) => Appearance {
+                mode: active_override.mode,
+                ..self.detected
+            },
+            None => self.detected,
+        }
+    }
+
+    fn time_until_next_deadline(&self) -> Option<Duration> {
+        let override_expiry = match self.active_override {
+            Some(Override {
+                expiry: OverrideExpiry::At(at),
+                ..
+            }) => Some(at),
+            _ => None,
+        };
+        let pause_expiry = self.paused.and_then(|pause| pause.until);
+
+        let deadline = [override_expiry, pause_expiry]
+            .into_iter()
+            .flatten()
+            .min()?;
+        let remaining = (deadline - Utc::now()).to_std().unwrap_or_default();
+
+        Some(remaining.min(MAX_SLEEP))
+    }
+
+    async fn expire(&mut self) {
+        let now = Utc::now();
+        let mut expired = None;
+
+        if let Some(Override {
+            expiry: OverrideExpiry::At(at),
+            ..
+        }) = self.active_override
+        {
+            if at <= now {
+                println!("😈 Override expired...");
+                self.active_override = None;
+                expired = Some(Trigger::Override);
+            }
+        }
+
+        if let Some(Pause { until: Some(until) }) = self.paused {
+            if until <= now {
+                println!("😈 Pause expired, resuming...");
+                self.paused = None;
+                expired = Some(Trigger::Resume);
+            }
+        }
+
+        if let Some(trigger) = expired {
+            self.reconcile(trigger).await;
+        }
+    }
+
+    /// Brings the scripts back in line with the effective appearance.
+    async fn reconcile(&mut self, trigger: Trigger) {
+        if self.paused.is_some() {
+            return;
+        }
+
+        let appearance = self.effective_appearance();
+        if self.applied != appearance {
+            self.apply(appearance, trigger).await;
+        } else if self.applied_accent_color != self.accent_color {
+            self.run_scripts(RunContext {
+                accent_color_only: true,
+                ..self.context(appearance, trigger)
+            })
+            .await;
+        }
+    }
+
+    async fn apply(&mut self, appearance: Appearance, trigger: Trigger) {
+        self.run_scripts(self.context(appearance, trigger)).await;
+        self.record(appearance, HistoryOutcome::Applied);
+    }
+
+    /// Detected changes either come from the OS, or from following a
+    /// schedule.
+    fn change_trigger(&self) -> Trigger {
+        match self.detector_kind {
+            DetectorKind::Native => Trigger::Change,
+            DetectorKind::Schedule => Trigger::Schedule,
+        }
+    }
+
+    fn context(&self, appearance: Appearance, trigger: Trigger) -> RunContext {
+        RunContext {
+            accent_color: self.accent_color,
+            previous_mode: Some(self.applied.mode),
+            detector: self.detector,
+            ..RunContext::new(appearance, trigger)
+        }
+    }
+
+    async fn run_scripts(&mut self, context: RunContext) {
+        let config = self.config.clone();
+        let verbose = self.verbose;
+        // Scripts are run synchronously, so we don't block the other tasks.
+        let report = tokio::task::spawn_blocking(move || {
+            let report = run(context, &config, &Selection::default(), verbose, true);
+            run_completion_hooks(&context, &report, &config, verbose);
+            report
+        })
+        .await;
+        if let Ok(report) = report {
+            self.sinks.publish(&context, report.run_id);
+            self.notifier.report(&report, context.appearance.mode).await;
+        }
+        self.applied = context.appearance;
+        self.applied_accent_color = context.accent_color;
+        self.last_run = Some(Utc::now());
+    }
+
+    fn record(&mut self, appearance: Appearance, outcome: HistoryOutcome) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(HistoryEntry {
+            at: Utc::now(),
+            mode: appearance.mode,
+            outcome,
+        });
+    }
+}
@@ -0,0 +1,55 @@
+use futures::{
+    stream::{self, LocalBoxStream},
+    StreamExt,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    config::Config,
+    mode::Appearance,
+    platform::{
+        backend::{self, ColorModeBackend},
+        ColorModeEvent,
+    },
+};
+
+/// The appearance the user currently prefers.
+///
+/// Uses the same detector as the daemon would, so a schedule configured in
+/// `config.toml` is taken into account.
+pub async fn current() -> anyhow::Result<Appearance> {
+    configured().await?.current_appearance().await
+}
+
+/// Every change to the appearance or accent color from now on.
+///
+/// Events are debounced and deduplicated like they are for the daemon. The
+/// stream never ends, and needs to be polled on the thread it was created on.
+pub async fn changes() -> anyhow::Result<LocalBoxStream<'static, ColorModeEvent>> {
+    Ok(into_stream(configured().await?))
+}
+
+async fn configured() -> anyhow::Result<Box<dyn ColorModeBackend>> {
+    let config = Config::load()?;
+    backend::select(config.detector, &config).await
+}
+
+/// Drives the backend as part of polling the returned stream.
+fn into_stream(backend: Box<dyn ColorModeBackend>) -> LocalBoxStream<'static, ColorModeEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let detect = async move {
+        backend
+            .start(Box::new(move |event| {
+                let _ = sender.send(event);
+            }))
+            .await;
+    };
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((event, receiver))
+    });
+    let detect = stream::once(detect).filter_map(|()| async { None });
+
+    stream::select(events, detect).boxed_local()
+}
@@ -1,10 +1,17 @@
+/// Settings scripts declare in their header.
+pub(crate) mod metadata;
+
 use xdg::BaseDirectories;
 
+use crate::config::HookEvent;
+use metadata::ScriptMetadata;
 use std::{
-    fs::{canonicalize, read_dir, ReadDir},
+    fmt::Display,
+    fs::{canonicalize, read_dir, DirEntry},
     iter::Iterator,
     os::unix::fs::PermissionsExt,
     path::PathBuf,
+    vec::IntoIter,
 };
 
 // FIXME: The errors should properly implement the Error trait so we can properly
@@ -33,6 +40,68 @@ pub(crate) struct ScriptsDirectoryEntry {
     /// The actual script location, wich may differ from
     /// [`ScriptsDirectoryEntry::script`], when dealing with symlinks.
     pub target: PathBuf,
+
+    /// Settings from the header of the script. Empty for everything that is
+    /// not a script.
+    pub metadata: ScriptMetadata,
+}
+
+/// Scripts run in stages, one after the other. All scripts of a stage run in
+/// parallel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Stage {
+    /// Scripts starting with a number, like `10-generate-theme.sh`.
+    Numbered(u32),
+
+    /// Scripts without a number run last.
+    Unnumbered,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Numbered(number) => write!(f, "{number}"),
+            Stage::Unnumbered => write!(f, "without a number"),
+        }
+    }
+}
+
+impl ScriptsDirectoryEntry {
+    /// The stage from the metadata, or derived from the numeric prefix of the
+    /// file name.
+    pub fn stage(&self) -> Stage {
+        if let Some(stage) = self.metadata.stage {
+            return Stage::Numbered(stage);
+        }
+
+        self.numeric_prefix()
+            .parse()
+            .map_or(Stage::Unnumbered, Stage::Numbered)
+    }
+
+    /// Warns about file names starting with a number too large for a stage,
+    /// which would otherwise silently run last.
+    fn stage_warning(&self) -> Option<String> {
+        let prefix = self.numeric_prefix();
+        if self.metadata.stage.is_some() || prefix.is_empty() || prefix.parse::<u32>().is_ok() {
+            return None;
+        }
+
+        Some(format!(
+            "Stage {prefix} is too large, the script runs without a number"
+        ))
+    }
+
+    /// The digits the file name starts with, e.g. `10` for `10-kitty.sh`.
+    fn numeric_prefix(&self) -> String {
+        let name = self
+            .script
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+
+        name.chars().take_while(char::is_ascii_digit).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -49,15 +118,25 @@ pub(crate) enum ScriptsDirectoryEntryError {
     },
 }
 
+/// Iterates the scripts in lexical order of their file names.
 pub(crate) struct ScriptsDirectory {
-    dir: ReadDir,
+    dir: IntoIter<std::io::Result<DirEntry>>,
     pub path: PathBuf,
 }
 
 impl ScriptsDirectory {
     pub fn read() -> Result<Self, ScriptsDirectoryError> {
+        Self::open("scripts")
+    }
+
+    /// Reads the scripts in `hooks/<event>/`, e.g. `hooks/on-start/`.
+    pub fn read_hooks(event: HookEvent) -> Result<Self, ScriptsDirectoryError> {
+        Self::open(&format!("hooks/{event}"))
+    }
+
+    fn open(name: &str) -> Result<Self, ScriptsDirectoryError> {
         let dmd_directory = BaseDirectories::with_prefix("dark-mode-daemon").unwrap();
-        let Some(scripts_directory) = dmd_directory.find_config_file("scripts") else {
+        let Some(scripts_directory) = dmd_directory.find_config_file(name) else {
             return Err(ScriptsDirectoryError::Missing);
         };
 
@@ -65,8 +144,11 @@ impl ScriptsDirectory {
             return Err(ScriptsDirectoryError::Unreadable(scripts_directory));
         };
 
+        let mut entries: Vec<_> = scripts_iterator.collect();
+        entries.sort_by_key(|entry| entry.as_ref().ok().map(DirEntry::file_name));
+
         Ok(Self {
-            dir: scripts_iterator,
+            dir: entries.into_iter(),
             path: scripts_directory,
         })
     }
@@ -111,6 +193,7 @@ impl Iterator for ScriptsDirectory {
                 kind: ScriptsDirectoryEntryKind::Directory,
                 script: original_path_buf,
                 target: path_buf,
+                metadata: ScriptMetadata::default(),
             }));
         }
 
@@ -120,13 +203,66 @@ impl Iterator for ScriptsDirectory {
                 kind: ScriptsDirectoryEntryKind::NonExecutableFile,
                 script: original_path_buf,
                 target: path_buf,
+                metadata: ScriptMetadata::default(),
             }));
         }
 
-        Some(Ok(ScriptsDirectoryEntry {
+        let mut entry = ScriptsDirectoryEntry {
             kind: ScriptsDirectoryEntryKind::Script,
             script: original_path_buf,
+            metadata: ScriptMetadata::read(&path_buf),
             target: path_buf,
-        }))
+        };
+        if let Some(warning) = entry.stage_warning() {
+            entry.metadata.warnings.push(warning);
+        }
+
+        Some(Ok(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(name: &str, metadata: ScriptMetadata) -> ScriptsDirectoryEntry {
+        ScriptsDirectoryEntry {
+            kind: ScriptsDirectoryEntryKind::Script,
+            script: PathBuf::from(name),
+            target: PathBuf::from(name),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn derives_the_stage_from_the_file_name() {
+        let numbered = script("10-kitty.sh", ScriptMetadata::default());
+        assert_eq!(numbered.stage(), Stage::Numbered(10));
+        assert_eq!(numbered.stage_warning(), None);
+
+        let unnumbered = script("kitty.sh", ScriptMetadata::default());
+        assert_eq!(unnumbered.stage(), Stage::Unnumbered);
+        assert_eq!(unnumbered.stage_warning(), None);
+    }
+
+    #[test]
+    fn warns_about_stages_too_large() {
+        let too_large = script("99999999999-kitty.sh", ScriptMetadata::default());
+
+        assert_eq!(too_large.stage(), Stage::Unnumbered);
+        assert_eq!(
+            too_large.stage_warning().unwrap(),
+            "Stage 99999999999 is too large, the script runs without a number"
+        );
+
+        let overridden = script(
+            "99999999999-kitty.sh",
+            ScriptMetadata {
+                stage: Some(20),
+                ..ScriptMetadata::default()
+            },
+        );
+        assert_eq!(overridden.stage(), Stage::Numbered(20));
+        assert_eq!(overridden.stage_warning(), None);
     }
 }
@@ -0,0 +1,307 @@
+use std::{
+    fmt::Display, fs::File, io::Read, num::NonZeroU32, path::Path, str::FromStr, time::Duration,
+};
+
+use clap::ValueEnum;
+
+use crate::{
+    config::{Backoff, RetryConfig, ScriptInput},
+    mode::ColorMode,
+};
+
+/// How much of a script we read when looking for metadata.
+const HEADER_SIZE: u64 = 8 * 1024;
+
+/// How many lines at the start of a script may contain metadata.
+const HEADER_LINES: usize = 20;
+
+/// What happens when a script fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OnFailure {
+    /// Keep running the remaining stages.
+    Continue,
+
+    /// Skip the remaining stages.
+    Abort,
+
+    /// Keep running the remaining stages, but show a desktop notification.
+    Notify,
+}
+
+/// Settings a script declares in its header, e.g.
+///
+/// ```sh
+/// #!/bin/sh
+/// # dmd: modes=dark timeout=5s stage=20 on-failure=abort
+/// ```
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ScriptMetadata {
+    /// Only run the script for these modes.
+    pub modes: Option<Vec<ColorMode>>,
+
+    /// Kill the script if it takes longer than this.
+    pub timeout: Option<Duration>,
+
+    /// Run the script in this stage, regardless of its file name.
+    pub stage: Option<u32>,
+
+    pub on_failure: Option<OnFailure>,
+
+    pub exclusive: bool,
+
+    pub on_accent_color_change: bool,
+
+    pub stdin: Option<ScriptInput>,
+
+    /// How often to run the script at most, when it fails.
+    pub attempts: Option<NonZeroU32>,
+
+    pub retry_delay: Option<Duration>,
+
+    pub backoff: Option<Backoff>,
+
+    /// Unknown keys and values that could not be parsed.
+    pub warnings: Vec<String>,
+}
+
+impl ScriptMetadata {
+    /// Reads the metadata from the header of the script at `path`.
+    pub fn read(path: &Path) -> Self {
+        let mut header = Vec::new();
+        let read =
+            File::open(path).and_then(|file| file.take(HEADER_SIZE).read_to_end(&mut header));
+        if let Err(error) = read {
+            return Self {
+                warnings: vec![format!("Could not read metadata: {error}")],
+                ..Self::default()
+            };
+        }
+
+        Self::parse(&String::from_utf8_lossy(&header))
+    }
+
+    /// Parses `dmd:` lines from the start of a script. The comment syntax of
+    /// most scripting languages is supported.
+    pub fn parse(header: &str) -> Self {
+        let mut metadata = Self::default();
+        for line in header.lines().take(HEADER_LINES) {
+            let line = line.trim_start_matches(|c: char| "#/-;".contains(c) || c.is_whitespace());
+            let Some(settings) = line.strip_prefix("dmd:") else {
+                continue;
+            };
+
+            for setting in settings.split_whitespace() {
+                let (key, value) = setting.split_once('=').unwrap_or((setting, "true"));
+                metadata.set(key, value);
+            }
+        }
+
+        metadata
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let valid = match key {
+            "modes" => value
+                .split(',')
+                .map(|mode| ColorMode::from_str(mode, true))
+                .collect::<Result<_, _>>()
+                .map(|modes| self.modes = Some(modes))
+                .is_ok(),
+            "timeout" => humantime::parse_duration(value)
+                .map(|timeout| self.timeout = Some(timeout))
+                .is_ok(),
+            "stage" => value.parse().map(|stage| self.stage = Some(stage)).is_ok(),
+            "on-failure" => OnFailure::from_str(value, true)
+                .map(|on_failure| self.on_failure = Some(on_failure))
+                .is_ok(),
+            "exclusive" => bool::from_str(value)
+                .map(|exclusive| self.exclusive = exclusive)
+                .is_ok(),
+            "attempts" => value
+                .parse()
+                .map(|attempts| self.attempts = Some(attempts))
+                .is_ok(),
+            "retry-delay" => humantime::parse_duration(value)
+                .map(|delay| self.retry_delay = Some(delay))
+                .is_ok(),
+            "backoff" => Backoff::from_str(value, true)
+                .map(|backoff| self.backoff = Some(backoff))
+                .is_ok(),
+            "stdin" => ScriptInput::from_str(value, true)
+                .map(|stdin| self.stdin = Some(stdin))
+                .is_ok(),
+            "on-accent-color-change" => bool::from_str(value)
+                .map(|on_accent_color_change| self.on_accent_color_change = on_accent_color_change)
+                .is_ok(),
+            _ => {
+                self.warnings.push(format!("Unknown key '{key}'"));
+                return;
+            }
+        };
+
+        if !valid {
+            self.warnings
+                .push(format!("Invalid value '{value}' for '{key}'"));
+        }
+    }
+
+    /// Applies the retry settings from the header on top of the `configured`
+    /// ones.
+    pub fn retry(&self, configured: RetryConfig) -> RetryConfig {
+        RetryConfig {
+            attempts: self.attempts.unwrap_or(configured.attempts),
+            delay: self.retry_delay.unwrap_or(configured.delay),
+            backoff: self.backoff.unwrap_or(configured.backoff),
+        }
+    }
+
+    /// Whether the script should run for the given mode.
+    pub fn runs_for(&self, mode: ColorMode) -> bool {
+        self.modes
+            .as_ref()
+            .is_none_or(|modes| modes.contains(&mode))
+    }
+}
+
+impl Display for ScriptMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut settings = Vec::new();
+        if let Some(modes) = &self.modes {
+            let modes: Vec<_> = modes.iter().map(ColorMode::to_string).collect();
+            settings.push(format!("modes={}", modes.join(",")));
+        }
+        if let Some(timeout) = self.timeout {
+            settings.push(format!("timeout={}", humantime::format_duration(timeout)));
+        }
+        if let Some(stage) = self.stage {
+            settings.push(format!("stage={stage}"));
+        }
+        if let Some(on_failure) = self.on_failure {
+            let on_failure = match on_failure {
+                OnFailure::Continue => "continue",
+                OnFailure::Abort => "abort",
+                OnFailure::Notify => "notify",
+            };
+            settings.push(format!("on-failure={on_failure}"));
+        }
+        if self.exclusive {
+            settings.push("exclusive".to_string());
+        }
+        if self.on_accent_color_change {
+            settings.push("on-accent-color-change".to_string());
+        }
+        if let Some(attempts) = self.attempts {
+            settings.push(format!("attempts={attempts}"));
+        }
+        if let Some(delay) = self.retry_delay {
+            settings.push(format!("retry-delay={}", humantime::format_duration(delay)));
+        }
+        if let Some(backoff) = self.backoff {
+            let backoff = match backoff {
+                Backoff::Fixed => "fixed",
+                Backoff::Exponential => "exponential",
+            };
+            settings.push(format!("backoff={backoff}"));
+        }
+        if let Some(stdin) = self.stdin {
+            let stdin = match stdin {
+                ScriptInput::Inherit => "inherit",
+                ScriptInput::Json => "json",
+            };
+            settings.push(format!("stdin={stdin}"));
+        }
+
+        write!(f, "{}", settings.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_settings_after_any_comment_prefix() {
+        let header = "#!/usr/bin/env python3
+# dmd: modes=light,dark timeout=1m30s
+// dmd: stage=20 on-failure=notify
+-- dmd: exclusive attempts=3
+; dmd: retry-delay=500ms backoff=exponential stdin=json
+    #  dmd: on-accent-color-change
+";
+
+        let metadata = ScriptMetadata::parse(header);
+
+        assert_eq!(
+            metadata.modes,
+            Some(vec![ColorMode::Light, ColorMode::Dark])
+        );
+        assert_eq!(metadata.timeout, Some(Duration::from_secs(90)));
+        assert_eq!(metadata.stage, Some(20));
+        assert_eq!(metadata.on_failure, Some(OnFailure::Notify));
+        assert!(metadata.exclusive);
+        assert_eq!(metadata.attempts, NonZeroU32::new(3));
+        assert_eq!(metadata.retry_delay, Some(Duration::from_millis(500)));
+        assert_eq!(metadata.backoff, Some(Backoff::Exponential));
+        assert_eq!(metadata.stdin, Some(ScriptInput::Json));
+        assert!(metadata.on_accent_color_change);
+        assert!(metadata.warnings.is_empty(), "{:?}", metadata.warnings);
+    }
+
+    #[test]
+    fn warns_about_unknown_keys_and_invalid_values() {
+        let metadata = ScriptMetadata::parse("# dmd: colour=dark modes=dusk timeout=soon");
+
+        assert_eq!(
+            metadata.warnings,
+            [
+                "Unknown key 'colour'",
+                "Invalid value 'dusk' for 'modes'",
+                "Invalid value 'soon' for 'timeout'",
+            ]
+        );
+        assert_eq!(metadata.modes, None);
+        assert_eq!(metadata.timeout, None);
+    }
+
+    #[test]
+    fn only_reads_the_first_lines() {
+        let header = format!("{}# dmd: modes=dark\n", "echo\n".repeat(HEADER_LINES));
+
+        assert_eq!(ScriptMetadata::parse(&header).modes, None);
+
+        let header = format!("{}# dmd: modes=dark\n", "echo\n".repeat(HEADER_LINES - 1));
+
+        assert_eq!(
+            ScriptMetadata::parse(&header).modes,
+            Some(vec![ColorMode::Dark])
+        );
+    }
+
+    #[test]
+    fn only_reads_the_first_kilobytes() {
+        let path = std::env::temp_dir().join(format!("dmd-metadata-{}", std::process::id()));
+        let padding = "#".repeat(HEADER_SIZE as usize);
+        std::fs::write(
+            &path,
+            format!("# dmd: stage=10\n{padding}\n# dmd: exclusive\n"),
+        )
+        .unwrap();
+
+        let metadata = ScriptMetadata::read(&path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(metadata.stage, Some(10));
+        assert!(!metadata.exclusive);
+    }
+
+    #[test]
+    fn displays_settings_as_they_are_parsed() {
+        let settings = "modes=dark timeout=5s stage=20 on-failure=abort exclusive \
+                        on-accent-color-change attempts=2 retry-delay=1s backoff=fixed \
+                        stdin=json";
+
+        let metadata = ScriptMetadata::parse(&format!("# dmd: {settings}"));
+
+        assert_eq!(metadata.to_string(), settings);
+    }
+}
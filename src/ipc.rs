@@ -0,0 +1,246 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc::UnboundedSender, oneshot},
+};
+use xdg::BaseDirectories;
+
+use crate::{daemon::Event, mode::ColorMode};
+
+/// How long to wait before accepting connections again after failing to.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Something the CLI asks a running daemon to do.
+///
+/// Requests and responses are exchanged as a single line of JSON each over a
+/// unix socket.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Request {
+    Status,
+    History,
+    Override(Override),
+    ClearOverride,
+    Pause(Pause),
+    Resume { run_scripts: bool },
+    Rerun,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Response {
+    Status(Status),
+    History { entries: Vec<HistoryEntry> },
+    Error { message: String },
+}
+
+/// A snapshot of what the daemon is currently doing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Status {
+    /// The mode reported by the detector.
+    pub detected_mode: ColorMode,
+
+    /// The mode the scripts were last run for.
+    pub applied_mode: ColorMode,
+
+    pub active_override: Option<Override>,
+
+    /// Whether changes are currently ignored.
+    pub paused: Option<Pause>,
+
+    /// When the scripts last finished running.
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+/// Applies a mode to the scripts regardless of what the detector reports.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Override {
+    pub mode: ColorMode,
+    pub expiry: OverrideExpiry,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "at", rename_all = "kebab-case")]
+pub enum OverrideExpiry {
+    At(DateTime<Utc>),
+    NextChange,
+}
+
+impl std::fmt::Display for OverrideExpiry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideExpiry::At(at) => {
+                write!(
+                    f,
+                    "until {}",
+                    at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                )
+            }
+            OverrideExpiry::NextChange => write!(f, "until the next change"),
+        }
+    }
+}
+
+/// Stops the daemon from running scripts.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Pause {
+    /// When to resume automatically, if at all.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Display for Pause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.until {
+            Some(until) => write!(
+                f,
+                "until {}",
+                until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            ),
+            None => write!(f, "until resumed"),
+        }
+    }
+}
+
+/// Something that happened while the daemon was running.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub at: DateTime<Utc>,
+    pub mode: ColorMode,
+    pub outcome: HistoryOutcome,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "reason", rename_all = "kebab-case")]
+pub enum HistoryOutcome {
+    /// The scripts were run for the mode.
+    Applied,
+
+    /// The mode was detected, but the scripts were not run.
+    Skipped(SkipReason),
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    Paused,
+    Overridden,
+}
+
+impl std::fmt::Display for HistoryOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryOutcome::Applied => write!(f, "applied"),
+            HistoryOutcome::Skipped(SkipReason::Paused) => write!(f, "skipped, paused"),
+            HistoryOutcome::Skipped(SkipReason::Overridden) => write!(f, "skipped, overridden"),
+        }
+    }
+}
+
+fn socket_path() -> io::Result<PathBuf> {
+    let dmd_directory = BaseDirectories::with_prefix("dark-mode-daemon")?;
+
+    // macOS does not have a runtime directory, so we fall back to the state
+    // directory there.
+    if dmd_directory.has_runtime_directory() {
+        dmd_directory.place_runtime_file("daemon.sock")
+    } else {
+        dmd_directory.place_state_file("daemon.sock")
+    }
+}
+
+/// Sends a request to the running daemon and waits for its response.
+pub async fn send(request: Request) -> anyhow::Result<Response> {
+    let path = socket_path()?;
+    let stream = UnixStream::connect(&path)
+        .await
+        .map_err(|_| anyhow!("The daemon does not seem to be running"))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut message = serde_json::to_string(&request)?;
+    message.push('\n');
+    writer.write_all(message.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .context("The daemon closed the connection without responding")?;
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Takes ownership of the socket, so the CLI can find this daemon.
+pub async fn listen() -> anyhow::Result<UnixListener> {
+    let path = socket_path()?;
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            return Err(anyhow!("Another daemon is already running"));
+        }
+
+        // Left behind by a daemon that did not shut down cleanly.
+        std::fs::remove_file(&path)?;
+    }
+
+    UnixListener::bind(&path).with_context(|| format!("Could not listen on '{}'", path.display()))
+}
+
+/// Removes the socket again when the daemon shuts down.
+pub fn unlisten() {
+    if let Ok(path) = socket_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Forwards requests from the CLI to the daemon.
+pub async fn serve(listener: UnixListener, events: UnboundedSender<Event>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                // E.g. running out of file descriptors, which does not go
+                // away by trying again right away.
+                println!("⚠️ Could not accept a connection: {error}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+
+        let events = events.clone();
+        tokio::spawn(async move {
+            // A misbehaving client should not affect the daemon.
+            let _ = handle_connection(stream, events).await;
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    events: UnboundedSender<Event>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let Some(line) = BufReader::new(reader).lines().next_line().await? else {
+        return Ok(());
+    };
+
+    let response = match serde_json::from_str(&line) {
+        Ok(request) => {
+            let (respond, response) = oneshot::channel();
+            events.send(Event::Request(request, respond))?;
+            response.await?
+        }
+        Err(error) => Response::Error {
+            message: format!("Invalid request: {error}"),
+        },
+    };
+
+    let mut message = serde_json::to_string(&response)?;
+    message.push('\n');
+    writer.write_all(message.as_bytes()).await?;
+
+    Ok(())
+}
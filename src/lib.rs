@@ -1,11 +1,31 @@
 /// Contains the command line interface.
 pub mod cli;
 
+/// The optional configuration file.
+pub mod config;
+
+/// The state of a running daemon.
+pub mod daemon;
+
+/// Stream based access to the color mode for embedding in other tools.
+pub mod detection;
+
 /// How and where to find the scripts to execute.
 pub mod discovery;
 
+/// Communication between the CLI and a running daemon.
+pub mod ipc;
+
 /// The color mode struct.
 pub mod mode;
 
+/// Desktop notifications about failed scripts.
+pub mod notifications;
+
 /// The platform-specific parts.
 pub mod platform;
+
+/// Other systems that are told about changes.
+pub mod sinks;
+
+pub use detection::{changes, current};
@@ -1,5 +1,4 @@
 use dark_mode_daemon::cli;
-use dark_mode_daemon::platform;
 use std::process::exit;
 fn handle_cli_result(result: anyhow::Result<()>) {
     match result {
@@ -11,18 +10,8 @@ fn handle_cli_result(result: anyhow::Result<()>) {
     }
 }
 
-#[cfg(target_os = "macos")]
 #[tokio::main]
 async fn main() {
-    let detector_factory = async || Ok(crate::platform::macos::MacOsColorModeDetector::default());
-    let cli_result = crate::cli::run(detector_factory).await;
-    handle_cli_result(cli_result);
-}
-
-#[cfg(target_os = "linux")]
-#[tokio::main]
-async fn main() {
-    let detector_factory = crate::platform::linux::LinuxColorModeDetector::default;
-    let cli_result = crate::cli::run(detector_factory).await;
+    let cli_result = crate::cli::run().await;
     handle_cli_result(cli_result);
 }
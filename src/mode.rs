@@ -1,8 +1,22 @@
 use clap::ValueEnum;
 use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, JsonSchema)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    ValueEnum,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum ColorMode {
     Light,
     Dark,
@@ -32,3 +46,71 @@ impl ColorMode {
         }
     }
 }
+
+/// Whether the user actively chose the color mode, or we fell back to a
+/// default because there was no preference.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorPreference {
+    Explicit,
+    Default,
+}
+
+impl Display for ColorPreference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorPreference::Explicit => write!(f, "explicit"),
+            ColorPreference::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Contrast {
+    Normal,
+    High,
+}
+
+impl Display for Contrast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Contrast::Normal => write!(f, "normal"),
+            Contrast::High => write!(f, "high"),
+        }
+    }
+}
+
+/// Everything we know about how the user wants things to look.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+pub struct Appearance {
+    pub mode: ColorMode,
+    pub preference: ColorPreference,
+    pub contrast: Contrast,
+}
+
+impl From<ColorMode> for Appearance {
+    /// For sources that only know about light and dark.
+    fn from(mode: ColorMode) -> Self {
+        Self {
+            mode,
+            preference: ColorPreference::Explicit,
+            contrast: Contrast::Normal,
+        }
+    }
+}
+
+/// The color the user picked for highlighting things.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+pub struct AccentColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Display for AccentColor {
+    /// Formats the color as hex, e.g. `#3584e4`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
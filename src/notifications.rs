@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use futures::future::BoxFuture;
+use tokio::time::Instant;
+
+use crate::{
+    cli::commands::{RunReport, ScriptKind, ScriptOutcome, ScriptResult},
+    config::NotificationsConfig,
+    mode::ColorMode,
+};
+
+/// A desktop notification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Something that can show notifications, e.g. the notification daemon of
+/// the desktop.
+pub trait NotificationServer: Send + Sync {
+    /// Shows the `notification`, in place of the one with the `replaces_id`
+    /// if that is still visible, and returns the ID of the new notification.
+    fn notify(
+        &self,
+        notification: Notification,
+        replaces_id: u32,
+    ) -> BoxFuture<'_, anyhow::Result<u32>>;
+}
+
+/// Tells the user about failed scripts, without flooding them with
+/// notifications if a script keeps failing.
+pub struct Notifier {
+    config: NotificationsConfig,
+    server: Option<Box<dyn NotificationServer>>,
+    last_sent: Option<Instant>,
+
+    /// Lets the desktop replace our last notification instead of stacking
+    /// another one on top.
+    last_id: u32,
+
+    /// How many failures happened since the last notification, that we did
+    /// not notify about because of the rate limit.
+    suppressed: usize,
+}
+
+impl Notifier {
+    /// Connects to the notification service once the first notification is
+    /// sent.
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self {
+            config,
+            server: None,
+            last_sent: None,
+            last_id: 0,
+            suppressed: 0,
+        }
+    }
+
+    pub fn with_server(config: NotificationsConfig, server: Box<dyn NotificationServer>) -> Self {
+        Self {
+            server: Some(server),
+            ..Self::new(config)
+        }
+    }
+
+    /// Shows a notification for the failed scripts of the `report`, that
+    /// either opted into notifications or are covered by the config.
+    pub async fn report(&mut self, report: &RunReport, mode: ColorMode) {
+        let failures: Vec<_> = report
+            .results
+            .iter()
+            .filter(|result| {
+                matches!(
+                    result.outcome,
+                    ScriptOutcome::Failed | ScriptOutcome::TimedOut
+                )
+            })
+            .filter(|result| result.notify || self.config.on_failure)
+            .collect();
+        if failures.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let is_rate_limited = self
+            .last_sent
+            .is_some_and(|last_sent| now.duration_since(last_sent) < self.config.min_interval);
+        if is_rate_limited {
+            self.suppressed += failures.len();
+            return;
+        }
+
+        let notification = self.notification(&failures, mode);
+        match self.send(notification).await {
+            Ok(id) => {
+                self.last_sent = Some(now);
+                self.last_id = id;
+                self.suppressed = 0;
+            }
+            Err(error) => println!("⚠️ Could not show a notification: {error}"),
+        }
+    }
+
+    fn notification(&self, failures: &[&ScriptResult], mode: ColorMode) -> Notification {
+        let summary = match failures {
+            [_] => format!("1 script failed while switching to {mode} mode"),
+            _ => format!(
+                "{} scripts failed while switching to {mode} mode",
+                failures.len()
+            ),
+        };
+
+        let mut lines: Vec<_> = failures.iter().map(|failure| describe(failure)).collect();
+        if self.suppressed > 0 {
+            lines.push(format!(
+                "…and {} more since the last notification",
+                self.suppressed
+            ));
+        }
+        lines.push(String::new());
+        let only: String = failures
+            .iter()
+            .map(|failure| format!(" --only '{}'", name(failure)))
+            .collect();
+        let output = match failures {
+            [_] => "its output",
+            _ => "their output",
+        };
+        lines.push(format!(
+            "Run `dark-mode-daemon run {mode}{only} --verbose` to see {output}."
+        ));
+
+        Notification {
+            summary,
+            body: lines.join("\n"),
+        }
+    }
+
+    async fn send(&mut self, notification: Notification) -> anyhow::Result<u32> {
+        let server = match &mut self.server {
+            Some(server) => server,
+            server => server.insert(connect().await?),
+        };
+
+        server.notify(notification, self.last_id).await
+    }
+}
+
+/// A line like `10-kitty.sh: exit status 1 (3 attempts)`.
+fn describe(failure: &ScriptResult) -> String {
+    let name = name(failure);
+
+    let last_attempt = failure.attempts.last();
+    let reason = match (
+        failure.outcome,
+        last_attempt.and_then(|attempt| attempt.exit_code),
+    ) {
+        (ScriptOutcome::TimedOut, _) => "timed out".to_string(),
+        (_, Some(exit_code)) => format!("exit status {exit_code}"),
+        (_, None) => "could not be started or was killed".to_string(),
+    };
+
+    match failure.attempts.len() {
+        0 | 1 => format!("{name}: {reason}"),
+        attempts => format!("{name}: {reason} ({attempts} attempts)"),
+    }
+}
+
+/// The file or hook name, which is what `run --only` matches against.
+fn name(failure: &ScriptResult) -> String {
+    match failure.kind {
+        ScriptKind::Script => Path::new(&failure.name)
+            .file_name()
+            .map_or(failure.name.clone(), |name| {
+                name.to_string_lossy().to_string()
+            }),
+        ScriptKind::Hook => failure.name.clone(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn connect() -> anyhow::Result<Box<dyn NotificationServer>> {
+    let notifications =
+        crate::platform::linux::notifications::FreedesktopNotifications::session().await?;
+    Ok(Box::new(notifications))
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn connect() -> anyhow::Result<Box<dyn NotificationServer>> {
+    Err(anyhow::anyhow!(
+        "Notifications are only supported on Linux for now"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::cli::commands::Attempt;
+
+    /// Remembers every notification and the ID it replaced.
+    #[derive(Clone, Default)]
+    struct RecordingServer {
+        sent: Arc<Mutex<Vec<(u32, Notification)>>>,
+    }
+
+    impl NotificationServer for RecordingServer {
+        fn notify(
+            &self,
+            notification: Notification,
+            replaces_id: u32,
+        ) -> BoxFuture<'_, anyhow::Result<u32>> {
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((replaces_id, notification));
+            let id = sent.len() as u32;
+            Box::pin(async move { Ok(id) })
+        }
+    }
+
+    fn notifier(on_failure: bool) -> (Notifier, RecordingServer) {
+        let server = RecordingServer::default();
+        let config = NotificationsConfig {
+            on_failure,
+            min_interval: Duration::from_secs(60),
+        };
+
+        (
+            Notifier::with_server(config, Box::new(server.clone())),
+            server,
+        )
+    }
+
+    fn result(
+        name: &str,
+        outcome: ScriptOutcome,
+        exit_codes: &[i32],
+        notify: bool,
+    ) -> ScriptResult {
+        ScriptResult {
+            kind: ScriptKind::Script,
+            name: format!("/scripts/{name}"),
+            outcome,
+            attempts: exit_codes
+                .iter()
+                .map(|exit_code| Attempt {
+                    started_at: Utc::now(),
+                    duration_ms: 0,
+                    outcome,
+                    exit_code: Some(*exit_code),
+                })
+                .collect(),
+            notify,
+        }
+    }
+
+    fn report(results: Vec<ScriptResult>) -> RunReport {
+        RunReport {
+            run_id: Uuid::new_v4(),
+            results,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_notifies_about_scripts_that_opted_in() {
+        let (mut notifier, server) = notifier(false);
+
+        let failed = report(vec![
+            result("10-quiet.sh", ScriptOutcome::Failed, &[1], false),
+            result("20-loud.sh", ScriptOutcome::Failed, &[2, 3], true),
+            result("30-fine.sh", ScriptOutcome::Succeeded, &[0], true),
+        ]);
+        notifier.report(&failed, ColorMode::Dark).await;
+
+        let sent = server.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].1.summary,
+            "1 script failed while switching to dark mode"
+        );
+        assert_eq!(
+            sent[0].1.body,
+            "20-loud.sh: exit status 3 (2 attempts)\n\nRun `dark-mode-daemon run dark --only '20-loud.sh' --verbose` to see its output."
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn notifies_about_every_failure_if_configured() {
+        let (mut notifier, server) = notifier(true);
+
+        let failed = report(vec![
+            result("10-quiet.sh", ScriptOutcome::Failed, &[1], false),
+            result("20-slow.sh", ScriptOutcome::TimedOut, &[], false),
+        ]);
+        notifier.report(&failed, ColorMode::Light).await;
+
+        let sent = server.sent.lock().unwrap();
+        assert_eq!(
+            sent[0].1.summary,
+            "2 scripts failed while switching to light mode"
+        );
+        assert_eq!(
+            sent[0].1.body,
+            "10-quiet.sh: exit status 1\n20-slow.sh: timed out\n\nRun `dark-mode-daemon run light --only '10-quiet.sh' --only '20-slow.sh' --verbose` to see their output."
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_notify_without_failures() {
+        let (mut notifier, server) = notifier(true);
+
+        let succeeded = report(vec![result(
+            "10-fine.sh",
+            ScriptOutcome::Succeeded,
+            &[0],
+            true,
+        )]);
+        notifier.report(&succeeded, ColorMode::Dark).await;
+
+        assert!(server.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limits_notifications() {
+        let (mut notifier, server) = notifier(true);
+        let failed = report(vec![result(
+            "10-broken.sh",
+            ScriptOutcome::Failed,
+            &[1],
+            false,
+        )]);
+
+        notifier.report(&failed, ColorMode::Dark).await;
+        tokio::time::advance(Duration::from_secs(10)).await;
+        notifier.report(&failed, ColorMode::Light).await;
+        notifier.report(&failed, ColorMode::Dark).await;
+        assert_eq!(server.sent.lock().unwrap().len(), 1);
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+        notifier.report(&failed, ColorMode::Light).await;
+
+        let sent = server.sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].0, 1, "replaces the previous notification");
+        assert!(sent[1]
+            .1
+            .body
+            .contains("…and 2 more since the last notification"));
+    }
+}
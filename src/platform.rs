@@ -4,35 +4,47 @@ pub mod macos;
 #[cfg(target_os = "linux")]
 pub mod linux;
 
-use crate::mode::ColorMode;
-use std::{error::Error, thread::sleep, time::Duration};
-
-/// Adapter that encapsulates platform-specific parts of dark mode daemon.
-/// TODO: This probably also needs a method for cleaning up the listener in the
-/// case of an interrupt or error!
-pub trait NativeAdapter {
-    /// Implementation of the deamon, which differs between OSes.
-    fn run_daemon<F>(
-        &self,
-        on_color_detected: F,
-        verbose: bool,
-    ) -> impl std::future::Future<Output = ()>
-    where
-        F: Fn(ColorMode);
+/// Time based detection that works the same on every platform.
+pub mod schedule;
 
-    /// Query the OS for the current color mode.
-    fn current_mode(&self) -> Result<ColorMode, Box<dyn Error>>;
+/// Filters out duplicate and flickering events of any daemon.
+pub mod debounce;
+
+/// Choosing a detector at runtime.
+pub mod backend;
+
+use crate::mode::{AccentColor, Appearance, ColorMode};
+use std::time::Duration;
+
+/// Something a [`ColorModeDaemon`] noticed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorModeEvent {
+    AppearanceChanged(Appearance),
+    AccentColorChanged(AccentColor),
 }
 
 pub trait ColorModeDaemon {
     fn on_color_changed<F>(&self, callback: F) -> impl std::future::Future<Output = ()>
     where
-        F: Fn(ColorMode) + 'static;
+        F: Fn(ColorModeEvent) + 'static;
 }
 
 pub trait ColorModeDetector {
     /// Query the OS for the current color mode.
     fn current_mode(&self) -> impl std::future::Future<Output = anyhow::Result<ColorMode>>;
+
+    /// Query the OS for the current color mode and everything else it knows
+    /// about the preferred appearance.
+    fn current_appearance(&self) -> impl std::future::Future<Output = anyhow::Result<Appearance>> {
+        async { Ok(Appearance::from(self.current_mode().await?)) }
+    }
+
+    /// Query the OS for the accent color, if it has one.
+    fn current_accent_color(
+        &self,
+    ) -> impl std::future::Future<Output = anyhow::Result<Option<AccentColor>>> {
+        async { Ok(None) }
+    }
 }
 
 pub struct PollingColorModeDaemon<Detector>
@@ -62,6 +74,14 @@ where
     async fn current_mode(&self) -> anyhow::Result<ColorMode> {
         self.detector.current_mode().await
     }
+
+    async fn current_appearance(&self) -> anyhow::Result<Appearance> {
+        self.detector.current_appearance().await
+    }
+
+    async fn current_accent_color(&self) -> anyhow::Result<Option<AccentColor>> {
+        self.detector.current_accent_color().await
+    }
 }
 
 impl<Detector> ColorModeDaemon for PollingColorModeDaemon<Detector>
@@ -70,15 +90,24 @@ where
 {
     async fn on_color_changed<F>(&self, callback: F)
     where
-        F: Fn(ColorMode),
+        F: Fn(ColorModeEvent),
     {
-        let mut previous_mode = self.detector.current_mode().await.unwrap();
+        let mut previous_appearance = self.detector.current_appearance().await.unwrap();
+        let mut previous_accent_color = self.detector.current_accent_color().await.unwrap();
         loop {
-            sleep(self.sleep_duration);
-            let current_mode = self.detector.current_mode().await.unwrap();
-            if previous_mode != current_mode {
-                previous_mode = current_mode;
-                callback(current_mode);
+            tokio::time::sleep(self.sleep_duration).await;
+            let current_appearance = self.detector.current_appearance().await.unwrap();
+            if previous_appearance != current_appearance {
+                previous_appearance = current_appearance;
+                callback(ColorModeEvent::AppearanceChanged(current_appearance));
+            }
+
+            let current_accent_color = self.detector.current_accent_color().await.unwrap();
+            if previous_accent_color != current_accent_color {
+                previous_accent_color = current_accent_color;
+                if let Some(accent_color) = current_accent_color {
+                    callback(ColorModeEvent::AccentColorChanged(accent_color));
+                }
             }
         }
     }
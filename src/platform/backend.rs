@@ -0,0 +1,215 @@
+use futures::future::LocalBoxFuture;
+use tokio::sync::watch;
+
+use crate::{
+    config::{Config, DetectorKind},
+    mode::{AccentColor, Appearance},
+    platform::{
+        debounce::DebouncedColorModeDaemon,
+        schedule::{Schedule, ScheduleColorModeDetector},
+        ColorModeDaemon, ColorModeDetector, ColorModeEvent,
+    },
+};
+
+/// A detector that can be chosen at runtime.
+///
+/// Unlike [`ColorModeDetector`] and [`ColorModeDaemon`], this can be used
+/// through a `Box<dyn ColorModeBackend>`.
+pub trait ColorModeBackend {
+    /// A short name for the detector, e.g. `xdg-portal`.
+    fn name(&self) -> &'static str;
+
+    /// Which kind of detector this is, e.g. to tell changes of the OS from
+    /// those of a schedule.
+    fn kind(&self) -> DetectorKind;
+
+    /// Query the current color mode and everything else known about the
+    /// preferred appearance.
+    fn current_appearance(&self) -> LocalBoxFuture<'_, anyhow::Result<Appearance>>;
+
+    /// Query the accent color, if there is one.
+    fn current_accent_color(&self) -> LocalBoxFuture<'_, anyhow::Result<Option<AccentColor>>>;
+
+    /// Calls `on_event` for every change, until [`Self::shutdown`] is called.
+    fn start(&self, on_event: Box<dyn Fn(ColorModeEvent)>) -> LocalBoxFuture<'_, ()>;
+
+    /// Stops listening for changes. A stopped backend can not be started again.
+    fn shutdown(&self);
+}
+
+/// Turns any detector into a [`ColorModeBackend`].
+pub struct DetectorBackend<Detector> {
+    kind: DetectorKind,
+    name: &'static str,
+    detector: Detector,
+    stopped: watch::Sender<bool>,
+}
+
+impl<Detector> DetectorBackend<Detector>
+where
+    Detector: ColorModeDaemon + ColorModeDetector + 'static,
+{
+    pub fn new(kind: DetectorKind, name: &'static str, detector: Detector) -> Self {
+        Self {
+            kind,
+            name,
+            detector,
+            stopped: watch::Sender::new(false),
+        }
+    }
+
+    pub fn boxed(self) -> Box<dyn ColorModeBackend> {
+        Box::new(self)
+    }
+}
+
+impl<Detector> ColorModeBackend for DetectorBackend<Detector>
+where
+    Detector: ColorModeDaemon + ColorModeDetector,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn kind(&self) -> DetectorKind {
+        self.kind
+    }
+
+    fn current_appearance(&self) -> LocalBoxFuture<'_, anyhow::Result<Appearance>> {
+        Box::pin(self.detector.current_appearance())
+    }
+
+    fn current_accent_color(&self) -> LocalBoxFuture<'_, anyhow::Result<Option<AccentColor>>> {
+        Box::pin(self.detector.current_accent_color())
+    }
+
+    fn start(&self, on_event: Box<dyn Fn(ColorModeEvent)>) -> LocalBoxFuture<'_, ()> {
+        let mut stopped = self.stopped.subscribe();
+        Box::pin(async move {
+            tokio::select! {
+                _ = self.detector.on_color_changed(on_event) => {}
+                _ = stopped.wait_for(|stopped| *stopped) => {}
+            }
+        })
+    }
+
+    fn shutdown(&self) {
+        self.stopped.send_replace(true);
+    }
+}
+
+/// Creates the backend for the given kind of detector.
+///
+/// Events are debounced, as configured in the `config`.
+pub async fn select(
+    kind: DetectorKind,
+    config: &Config,
+) -> anyhow::Result<Box<dyn ColorModeBackend>> {
+    let window = config.debounce;
+    let backend = match kind {
+        DetectorKind::Native => native(window).await?,
+        DetectorKind::Schedule => {
+            let schedule = Schedule::from_config(config)?;
+            let detector = ScheduleColorModeDetector::new(schedule);
+            DetectorBackend::new(
+                DetectorKind::Schedule,
+                "schedule",
+                DebouncedColorModeDaemon::new(detector, window),
+            )
+            .boxed()
+        }
+    };
+
+    Ok(backend)
+}
+
+#[cfg(target_os = "linux")]
+async fn native(window: std::time::Duration) -> anyhow::Result<Box<dyn ColorModeBackend>> {
+    let detector = crate::platform::linux::LinuxColorModeDetector::default().await?;
+    Ok(DetectorBackend::new(
+        DetectorKind::Native,
+        "xdg-portal",
+        DebouncedColorModeDaemon::new(detector, window),
+    )
+    .boxed())
+}
+
+#[cfg(target_os = "macos")]
+async fn native(window: std::time::Duration) -> anyhow::Result<Box<dyn ColorModeBackend>> {
+    let detector = crate::platform::macos::MacOsColorModeDetector::default();
+    Ok(DetectorBackend::new(
+        DetectorKind::Native,
+        "macos",
+        DebouncedColorModeDaemon::new(detector, window),
+    )
+    .boxed())
+}
+
+/// A backend for tests.
+#[cfg(test)]
+pub(crate) mod fake {
+    use std::{cell::RefCell, collections::VecDeque};
+
+    use super::*;
+    use crate::mode::ColorMode;
+
+    /// Reports the `modes` when asked, one after the other and then the last
+    /// one, and the `changes` once started.
+    pub struct FakeBackend {
+        pub modes: RefCell<VecDeque<ColorMode>>,
+        pub accent_color: Option<AccentColor>,
+        pub changes: Vec<ColorMode>,
+    }
+
+    impl FakeBackend {
+        /// Always in the `mode`, without an accent color or any changes.
+        pub fn new(mode: ColorMode) -> Self {
+            Self::switching(vec![mode])
+        }
+
+        /// Switches to the next of the `modes` every time it is asked.
+        pub fn switching(modes: Vec<ColorMode>) -> Self {
+            Self {
+                modes: RefCell::new(VecDeque::from(modes)),
+                accent_color: None,
+                changes: Vec::new(),
+            }
+        }
+    }
+
+    impl ColorModeBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn kind(&self) -> DetectorKind {
+            DetectorKind::Native
+        }
+
+        fn current_appearance(&self) -> LocalBoxFuture<'_, anyhow::Result<Appearance>> {
+            let mut modes = self.modes.borrow_mut();
+            let mode = if modes.len() > 1 {
+                modes.pop_front()
+            } else {
+                modes.front().copied()
+            };
+            Box::pin(async move { Ok(Appearance::from(mode.unwrap())) })
+        }
+
+        fn current_accent_color(&self) -> LocalBoxFuture<'_, anyhow::Result<Option<AccentColor>>> {
+            Box::pin(async { Ok(self.accent_color) })
+        }
+
+        fn start(&self, on_event: Box<dyn Fn(ColorModeEvent)>) -> LocalBoxFuture<'_, ()> {
+            Box::pin(async move {
+                for mode in &self.changes {
+                    let appearance = Appearance::from(*mode);
+                    on_event(ColorModeEvent::AppearanceChanged(appearance));
+                }
+                std::future::pending().await
+            })
+        }
+
+        fn shutdown(&self) {}
+    }
+}
@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::{
+    mode::{AccentColor, Appearance, ColorMode},
+    platform::{ColorModeDaemon, ColorModeDetector, ColorModeEvent},
+};
+
+/// Wraps any daemon, so that callbacks only happen for actual changes.
+///
+/// Some platforms report a single change multiple times, or a desktop might
+/// flicker between modes while a user clicks through its settings. We wait
+/// until no further event arrives for the duration of the `window`, and then
+/// only pass on what differs from the last values we passed on.
+///
+/// This compares against what the detector reported, not against what the
+/// daemon applied. The two differ during overrides and pauses, which the
+/// daemon tracks itself, and an unchanged OS mode should not end those.
+pub struct DebouncedColorModeDaemon<Daemon> {
+    daemon: Daemon,
+    window: Duration,
+}
+
+impl<Daemon> DebouncedColorModeDaemon<Daemon> {
+    pub fn new(daemon: Daemon, window: Duration) -> Self {
+        Self { daemon, window }
+    }
+}
+
+/// What was last passed on to the callback, i.e. detected, which is not
+/// necessarily what the daemon ran the scripts for.
+struct Forwarded {
+    appearance: Option<Appearance>,
+    accent_color: Option<AccentColor>,
+}
+
+impl Forwarded {
+    /// Returns the events to pass on once a burst of events settled.
+    fn settle(&mut self, burst: Vec<ColorModeEvent>) -> Vec<ColorModeEvent> {
+        let mut appearance = None;
+        let mut accent_color = None;
+        for event in burst {
+            match event {
+                ColorModeEvent::AppearanceChanged(changed) => appearance = Some(changed),
+                ColorModeEvent::AccentColorChanged(changed) => accent_color = Some(changed),
+            }
+        }
+
+        let mut events = Vec::new();
+        if appearance.is_some() && appearance != self.appearance {
+            self.appearance = appearance;
+            events.extend(appearance.map(ColorModeEvent::AppearanceChanged));
+        }
+        if accent_color.is_some() && accent_color != self.accent_color {
+            self.accent_color = accent_color;
+            events.extend(accent_color.map(ColorModeEvent::AccentColorChanged));
+        }
+
+        events
+    }
+}
+
+impl<Daemon> ColorModeDetector for DebouncedColorModeDaemon<Daemon>
+where
+    Daemon: ColorModeDetector,
+{
+    async fn current_mode(&self) -> anyhow::Result<ColorMode> {
+        self.daemon.current_mode().await
+    }
+
+    async fn current_appearance(&self) -> anyhow::Result<Appearance> {
+        self.daemon.current_appearance().await
+    }
+
+    async fn current_accent_color(&self) -> anyhow::Result<Option<AccentColor>> {
+        self.daemon.current_accent_color().await
+    }
+}
+
+impl<Daemon> ColorModeDaemon for DebouncedColorModeDaemon<Daemon>
+where
+    Daemon: ColorModeDaemon + ColorModeDetector,
+{
+    async fn on_color_changed<F>(&self, callback: F)
+    where
+        F: Fn(ColorModeEvent),
+    {
+        // Whoever uses the daemon usually acts on the current values right
+        // away, so reporting them again would be a duplicate.
+        let mut forwarded = Forwarded {
+            appearance: self.daemon.current_appearance().await.ok(),
+            accent_color: self.daemon.current_accent_color().await.ok().flatten(),
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let detect = self.daemon.on_color_changed(move |event| {
+            let _ = sender.send(event);
+        });
+        let debounce = async {
+            while let Some(event) = receiver.recv().await {
+                let mut burst = vec![event];
+                while let Ok(Some(event)) = tokio::time::timeout(self.window, receiver.recv()).await
+                {
+                    burst.push(event);
+                }
+
+                for event in forwarded.settle(burst) {
+                    callback(event);
+                }
+            }
+        };
+
+        futures::join!(detect, debounce);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::time::Instant;
+
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(250);
+
+    const LIGHT: ColorModeEvent = ColorModeEvent::AppearanceChanged(appearance(ColorMode::Light));
+    const DARK: ColorModeEvent = ColorModeEvent::AppearanceChanged(appearance(ColorMode::Dark));
+
+    const fn appearance(mode: ColorMode) -> Appearance {
+        Appearance {
+            mode,
+            preference: crate::mode::ColorPreference::Explicit,
+            contrast: crate::mode::Contrast::Normal,
+        }
+    }
+
+    fn accent(red: u8) -> ColorModeEvent {
+        ColorModeEvent::AccentColorChanged(AccentColor {
+            red,
+            green: 0,
+            blue: 0,
+        })
+    }
+
+    /// Emits the given events, each after waiting for its delay, and keeps
+    /// running for a while afterwards like a real daemon would.
+    struct SyntheticDaemon {
+        initial: ColorMode,
+        events: Vec<(u64, ColorModeEvent)>,
+    }
+
+    impl ColorModeDetector for SyntheticDaemon {
+        async fn current_mode(&self) -> anyhow::Result<ColorMode> {
+            Ok(self.initial)
+        }
+
+        async fn current_appearance(&self) -> anyhow::Result<Appearance> {
+            Ok(appearance(self.initial))
+        }
+    }
+
+    impl ColorModeDaemon for SyntheticDaemon {
+        async fn on_color_changed<F>(&self, callback: F)
+        where
+            F: Fn(ColorModeEvent),
+        {
+            for (delay, event) in &self.events {
+                tokio::time::sleep(Duration::from_millis(*delay)).await;
+                callback(*event);
+            }
+
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    }
+
+    /// Returns the forwarded events along with the milliseconds after which
+    /// they were forwarded.
+    async fn debounce(
+        initial: ColorMode,
+        events: Vec<(u64, ColorModeEvent)>,
+    ) -> Vec<(u128, ColorModeEvent)> {
+        let daemon = DebouncedColorModeDaemon::new(SyntheticDaemon { initial, events }, WINDOW);
+        let forwarded = Arc::new(Mutex::new(Vec::new()));
+        let start = Instant::now();
+
+        let collected = forwarded.clone();
+        daemon
+            .on_color_changed(move |event| {
+                let elapsed = start.elapsed().as_millis();
+                collected.lock().unwrap().push((elapsed, event));
+            })
+            .await;
+
+        let forwarded = forwarded.lock().unwrap().clone();
+        forwarded
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn forwards_changes_after_the_window() {
+        let forwarded = debounce(ColorMode::Light, vec![(1000, DARK)]).await;
+
+        assert_eq!(forwarded, vec![(1250, DARK)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn suppresses_the_current_mode() {
+        let forwarded = debounce(ColorMode::Light, vec![(1000, LIGHT)]).await;
+
+        assert_eq!(forwarded, vec![]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn suppresses_duplicates() {
+        let forwarded = debounce(
+            ColorMode::Light,
+            vec![(1000, DARK), (10, DARK), (1000, DARK)],
+        )
+        .await;
+
+        assert_eq!(forwarded, vec![(1260, DARK)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_forwards_the_last_event_of_a_burst() {
+        let forwarded = debounce(
+            ColorMode::Light,
+            vec![(1000, DARK), (100, LIGHT), (100, DARK)],
+        )
+        .await;
+
+        assert_eq!(forwarded, vec![(1450, DARK)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ignores_bursts_ending_in_the_forwarded_mode() {
+        let forwarded = debounce(ColorMode::Light, vec![(1000, DARK), (100, LIGHT)]).await;
+
+        assert_eq!(forwarded, vec![]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn forwards_changes_outside_of_the_window_separately() {
+        let forwarded = debounce(ColorMode::Light, vec![(1000, DARK), (1000, LIGHT)]).await;
+
+        assert_eq!(forwarded, vec![(1250, DARK), (2250, LIGHT)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounces_accent_colors_independently() {
+        let forwarded = debounce(
+            ColorMode::Light,
+            vec![
+                (1000, accent(1)),
+                (100, DARK),
+                (100, accent(2)),
+                (1000, accent(2)),
+            ],
+        )
+        .await;
+
+        assert_eq!(forwarded, vec![(1450, DARK), (1450, accent(2))]);
+    }
+}
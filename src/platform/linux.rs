@@ -1,15 +1,25 @@
+pub mod appearance;
 pub mod autostart;
+pub mod notifications;
+pub mod service;
 
-use ashpd::desktop::settings::{ColorScheme as GnomeColorMode, Settings as GnomeSettings};
-use futures::StreamExt;
+use std::{fmt::Display, future::Future, time::Duration};
 
-use crate::{
-    mode::ColorMode,
-    platform::{ColorModeDaemon, ColorModeDetector},
+use ashpd::desktop::{
+    settings::{
+        ColorScheme as GnomeColorMode, Contrast as GnomeContrast, Settings as GnomeSettings,
+    },
+    Color as GnomeColor,
+};
+use futures::{
+    stream::{self, LocalBoxStream},
+    StreamExt,
 };
 
-#[derive(Default)]
-pub struct LinuxAdapter {}
+use crate::{
+    mode::{AccentColor, Appearance, ColorMode, ColorPreference, Contrast},
+    platform::{ColorModeDaemon, ColorModeDetector, ColorModeEvent},
+};
 
 impl From<GnomeColorMode> for ColorMode {
     fn from(value: GnomeColorMode) -> Self {
@@ -21,6 +31,55 @@ impl From<GnomeColorMode> for ColorMode {
     }
 }
 
+impl From<GnomeColorMode> for ColorPreference {
+    fn from(value: GnomeColorMode) -> Self {
+        match value {
+            GnomeColorMode::NoPreference => ColorPreference::Default,
+            GnomeColorMode::PreferDark | GnomeColorMode::PreferLight => ColorPreference::Explicit,
+        }
+    }
+}
+
+impl From<GnomeContrast> for Contrast {
+    fn from(value: GnomeContrast) -> Self {
+        match value {
+            GnomeContrast::NoPreference => Contrast::Normal,
+            GnomeContrast::High => Contrast::High,
+        }
+    }
+}
+
+fn appearance(color_scheme: GnomeColorMode, contrast: GnomeContrast) -> Appearance {
+    Appearance {
+        mode: ColorMode::from(color_scheme),
+        preference: ColorPreference::from(color_scheme),
+        contrast: Contrast::from(contrast),
+    }
+}
+
+/// The portal uses values outside of the `[0, 1]` range when no accent color
+/// is set.
+fn accent_color(color: GnomeColor) -> Option<AccentColor> {
+    let channel = |value: f64| {
+        (0.0..=1.0)
+            .contains(&value)
+            .then(|| (value * 255.0).round() as u8)
+    };
+
+    Some(AccentColor {
+        red: channel(color.red())?,
+        green: channel(color.green())?,
+        blue: channel(color.blue())?,
+    })
+}
+
+/// A single setting of the portal that changed.
+enum SettingChange {
+    ColorScheme(GnomeColorMode),
+    Contrast(GnomeContrast),
+    AccentColor(GnomeColor),
+}
+
 pub struct LinuxColorModeDetector<'a> {
     settings: GnomeSettings<'a>,
 }
@@ -35,32 +94,306 @@ impl<'a> LinuxColorModeDetector<'a> {
 
 impl<'a> ColorModeDetector for LinuxColorModeDetector<'a> {
     async fn current_mode(&self) -> anyhow::Result<ColorMode> {
-        let color_scheme = self.settings.color_scheme().await?;
+        let color_scheme = self.settings.current_color_scheme().await?;
         Ok(ColorMode::from(color_scheme))
     }
+
+    async fn current_appearance(&self) -> anyhow::Result<Appearance> {
+        let color_scheme = self.settings.current_color_scheme().await?;
+        Ok(appearance(
+            color_scheme,
+            self.settings.current_contrast().await,
+        ))
+    }
+
+    async fn current_accent_color(&self) -> anyhow::Result<Option<AccentColor>> {
+        Ok(self
+            .settings
+            .current_accent_color()
+            .await
+            .and_then(accent_color))
+    }
 }
 
 impl<'a> ColorModeDaemon for LinuxColorModeDetector<'a> {
     async fn on_color_changed<F>(&self, callback: F)
     where
-        F: Fn(ColorMode),
+        F: Fn(ColorModeEvent),
     {
-        let mut color_mode_changes = self
-            .settings
+        follow(&self.settings, GnomeSettings::new, callback).await
+    }
+}
+
+/// What we use of the settings portal, so following it can be tested without
+/// one.
+trait Portal {
+    async fn current_color_scheme(&self) -> anyhow::Result<GnomeColorMode>;
+
+    /// Older portals do not know about contrast, so we assume there is no
+    /// preference in that case.
+    async fn current_contrast(&self) -> GnomeContrast;
+
+    /// Only recent portals support accent colors, so we treat failures as
+    /// the accent color not being set.
+    async fn current_accent_color(&self) -> Option<GnomeColor>;
+
+    /// The changes of the color scheme, which end once the connection is
+    /// lost, and the changes of everything else.
+    async fn changes(&self) -> anyhow::Result<(Changes<'_>, Changes<'_>)>;
+}
+
+type Changes<'a> = LocalBoxStream<'a, SettingChange>;
+
+impl<'a> Portal for GnomeSettings<'a> {
+    async fn current_color_scheme(&self) -> anyhow::Result<GnomeColorMode> {
+        Ok(self.color_scheme().await?)
+    }
+
+    async fn current_contrast(&self) -> GnomeContrast {
+        self.contrast().await.unwrap_or_default()
+    }
+
+    async fn current_accent_color(&self) -> Option<GnomeColor> {
+        self.accent_color().await.ok()
+    }
+
+    async fn changes(&self) -> anyhow::Result<(Changes<'_>, Changes<'_>)> {
+        let color_scheme_changes = self
             .receive_color_scheme_changed()
-            .await
-            .unwrap()
-            .map(|color_scheme| ColorMode::from(color_scheme));
-
-        // Tests showed, that for some reason the mode change is triggered twice
-        // right after one another. To prevent from running our scripts twice, we
-        // do some deduplication here.
-        let mut previous_mode: Option<ColorMode> = None;
-        while let Some(mode) = color_mode_changes.next().await {
-            if mode != previous_mode.unwrap_or_else(|| mode.other()) {
-                previous_mode = Some(mode);
-                callback(mode);
+            .await?
+            .map(SettingChange::ColorScheme);
+        let contrast_changes = match self.receive_contrast_changed().await {
+            Ok(changes) => changes.map(SettingChange::Contrast).left_stream(),
+            Err(_) => stream::empty().right_stream(),
+        };
+        let accent_color_changes = match self.receive_accent_color_changed().await {
+            Ok(changes) => changes.map(SettingChange::AccentColor).left_stream(),
+            Err(_) => stream::empty().right_stream(),
+        };
+
+        Ok((
+            color_scheme_changes.boxed_local(),
+            stream::select(contrast_changes, accent_color_changes).boxed_local(),
+        ))
+    }
+}
+
+/// Calls the `callback` for every change of the `portal`, reconnecting with
+/// `connect` whenever the connection is lost.
+async fn follow<P, Connect, Connecting, E, F>(portal: &P, connect: Connect, callback: F)
+where
+    P: Portal,
+    Connect: Fn() -> Connecting,
+    Connecting: Future<Output = Result<P, E>>,
+    E: Display,
+    F: Fn(ColorModeEvent),
+{
+    let mut state = PortalState {
+        color_scheme: portal.current_color_scheme().await.unwrap_or_default(),
+        contrast: portal.current_contrast().await,
+    };
+
+    let mut reconnected: Option<P> = None;
+    let mut backoff = MIN_RECONNECT_DELAY;
+    loop {
+        let portal = reconnected.as_ref().unwrap_or(portal);
+        match watch(portal, &mut state, &callback).await {
+            Ok(()) => println!("😈 Lost connection to the XDG desktop portal..."),
+            Err(error) => println!("❌ Failed listening to the XDG desktop portal: {error}"),
+        }
+
+        // The portal might be restarting, e.g. after an update, so we try
+        // again with increasing delays until it is back.
+        let portal = loop {
+            println!("😈 Reconnecting in {}s...", backoff.as_secs());
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RECONNECT_DELAY);
+
+            match connect().await {
+                Ok(portal) => break portal,
+                Err(error) => println!("❌ Failed to reconnect: {error}"),
+            }
+        };
+
+        // We might have missed changes while disconnected.
+        match resync(&portal, &mut state).await {
+            Ok(events) => {
+                println!("😈 Reconnected to the XDG desktop portal");
+                backoff = MIN_RECONNECT_DELAY;
+                events.into_iter().for_each(&callback);
+            }
+            Err(error) => println!("❌ Failed to query the XDG desktop portal: {error}"),
+        }
+
+        reconnected = Some(portal);
+    }
+}
+
+/// How long we wait before reconnecting for the first time.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest we wait between two attempts of reconnecting.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// What we know about the portal settings, kept across reconnects.
+///
+/// The signals only contain the setting that changed, so we need to keep track
+/// of the others ourselves.
+struct PortalState {
+    color_scheme: GnomeColorMode,
+    contrast: GnomeContrast,
+}
+
+impl PortalState {
+    /// Returns the event to emit for the change.
+    ///
+    /// Tests showed, that for some reason the mode change is triggered twice
+    /// right after one another, so the daemon needs to be wrapped in a
+    /// [`crate::platform::debounce::DebouncedColorModeDaemon`] to not run our scripts twice.
+    fn update(&mut self, change: SettingChange) -> Option<ColorModeEvent> {
+        match change {
+            SettingChange::ColorScheme(changed) => self.color_scheme = changed,
+            SettingChange::Contrast(changed) => self.contrast = changed,
+            SettingChange::AccentColor(changed) => {
+                return accent_color(changed).map(ColorModeEvent::AccentColorChanged);
             }
         }
+
+        Some(ColorModeEvent::AppearanceChanged(appearance(
+            self.color_scheme,
+            self.contrast,
+        )))
+    }
+}
+
+/// Forwards changes to the callback until the connection is lost.
+async fn watch<F>(portal: &impl Portal, state: &mut PortalState, callback: &F) -> anyhow::Result<()>
+where
+    F: Fn(ColorModeEvent),
+{
+    let (mut color_scheme_changes, other_changes) = portal.changes().await?;
+    // Older portals do not support contrast and accent colors, so only the
+    // color scheme changes ending means we lost the connection.
+    let mut other_changes = other_changes.fuse();
+
+    loop {
+        let change = tokio::select! {
+            change = color_scheme_changes.next() => match change {
+                Some(change) => change,
+                None => return Ok(()),
+            },
+            Some(change) = other_changes.next() => change,
+        };
+
+        if let Some(event) = state.update(change) {
+            callback(event);
+        }
+    }
+}
+
+/// Queries the current settings and returns events for everything that
+/// might have changed since we last heard from the portal.
+async fn resync(
+    portal: &impl Portal,
+    state: &mut PortalState,
+) -> anyhow::Result<Vec<ColorModeEvent>> {
+    let color_scheme = portal.current_color_scheme().await?;
+    let contrast = portal.current_contrast().await;
+    let previous = appearance(state.color_scheme, state.contrast);
+
+    let mut events = Vec::new();
+    state.contrast = contrast;
+    if appearance(color_scheme, contrast) != previous {
+        events.extend(state.update(SettingChange::ColorScheme(color_scheme)));
+    }
+    if let Some(accent_color) = portal.current_accent_color().await {
+        events.extend(state.update(SettingChange::AccentColor(accent_color)));
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// Reports the `changes` of the color scheme, and loses the connection
+    /// after them if it `disconnects`.
+    struct FakePortal {
+        color_scheme: GnomeColorMode,
+        changes: Vec<GnomeColorMode>,
+        disconnects: bool,
+    }
+
+    impl Portal for FakePortal {
+        async fn current_color_scheme(&self) -> anyhow::Result<GnomeColorMode> {
+            Ok(self.color_scheme)
+        }
+
+        async fn current_contrast(&self) -> GnomeContrast {
+            GnomeContrast::NoPreference
+        }
+
+        async fn current_accent_color(&self) -> Option<GnomeColor> {
+            None
+        }
+
+        async fn changes(&self) -> anyhow::Result<(Changes<'_>, Changes<'_>)> {
+            let changes = stream::iter(self.changes.clone()).map(SettingChange::ColorScheme);
+            let color_scheme_changes = if self.disconnects {
+                changes.boxed_local()
+            } else {
+                changes.chain(stream::pending()).boxed_local()
+            };
+
+            Ok((color_scheme_changes, stream::pending().boxed_local()))
+        }
+    }
+
+    fn changed(color_scheme: GnomeColorMode) -> ColorModeEvent {
+        ColorModeEvent::AppearanceChanged(appearance(color_scheme, GnomeContrast::NoPreference))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnects_once_the_connection_is_lost() {
+        let portal = FakePortal {
+            color_scheme: GnomeColorMode::PreferLight,
+            changes: vec![GnomeColorMode::PreferDark],
+            disconnects: true,
+        };
+        // The portal is still restarting on the first attempt, and switched
+        // back to light mode in the meantime.
+        let attempts = Rc::new(RefCell::new(0));
+        let connect = || {
+            let attempts = attempts.clone();
+            async move {
+                *attempts.borrow_mut() += 1;
+                if *attempts.borrow() == 1 {
+                    return Err("The portal is not running");
+                }
+
+                Ok(FakePortal {
+                    color_scheme: GnomeColorMode::PreferLight,
+                    changes: Vec::new(),
+                    disconnects: false,
+                })
+            }
+        };
+        let events = RefCell::new(Vec::new());
+
+        let following = follow(&portal, connect, |event| events.borrow_mut().push(event));
+        let result = tokio::time::timeout(Duration::from_secs(60), following).await;
+
+        assert!(result.is_err(), "Stopped following the portal");
+        assert_eq!(*attempts.borrow(), 2);
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                changed(GnomeColorMode::PreferDark),
+                changed(GnomeColorMode::PreferLight)
+            ]
+        );
     }
 }
@@ -0,0 +1,112 @@
+use std::{env, process::Command};
+
+use anyhow::{anyhow, Context};
+use ashpd::zbus;
+
+use crate::mode::ColorMode;
+
+/// The ways we know of for changing the color scheme on Linux.
+enum Backend {
+    /// GNOME and most desktops derived from it.
+    GSettings,
+
+    /// KDE Plasma.
+    Kde,
+}
+
+impl Backend {
+    fn detect() -> anyhow::Result<Self> {
+        let desktops = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        let is_kde = desktops
+            .split(':')
+            .any(|desktop| desktop.eq_ignore_ascii_case("KDE"));
+        if is_kde {
+            return Ok(Backend::Kde);
+        }
+
+        if is_installed("gsettings") {
+            return Ok(Backend::GSettings);
+        }
+
+        Err(anyhow!(
+            "Don't know how to change the color scheme of your desktop ('{desktops}'). Only GNOME (via gsettings) and KDE Plasma are supported."
+        ))
+    }
+}
+
+/// Changes the color scheme of the whole desktop.
+pub async fn set_color_mode(mode: ColorMode) -> anyhow::Result<()> {
+    match Backend::detect()? {
+        Backend::GSettings => set_gsettings_color_scheme(mode),
+        Backend::Kde => set_kde_color_scheme(mode).await,
+    }
+}
+
+fn set_gsettings_color_scheme(mode: ColorMode) -> anyhow::Result<()> {
+    // GNOME itself uses `default` instead of `prefer-light` for light mode.
+    let color_scheme = match mode {
+        ColorMode::Light => "default",
+        ColorMode::Dark => "prefer-dark",
+    };
+
+    execute(
+        Command::new("gsettings")
+            .arg("set")
+            .arg("org.gnome.desktop.interface")
+            .arg("color-scheme")
+            .arg(color_scheme),
+    )
+}
+
+async fn set_kde_color_scheme(mode: ColorMode) -> anyhow::Result<()> {
+    let color_scheme = match mode {
+        ColorMode::Light => "BreezeLight",
+        ColorMode::Dark => "BreezeDark",
+    };
+
+    let kwriteconfig = ["kwriteconfig6", "kwriteconfig5"]
+        .into_iter()
+        .find(|program| is_installed(program))
+        .context("Neither kwriteconfig6 nor kwriteconfig5 could be found")?;
+    execute(
+        Command::new(kwriteconfig)
+            .args(["--file", "kdeglobals"])
+            .args(["--group", "General"])
+            .args(["--key", "ColorScheme"])
+            .arg(color_scheme),
+    )?;
+
+    // Running applications only re-read kdeglobals when being told so.
+    const PALETTE_CHANGED: i32 = 0;
+    let connection = zbus::Connection::session().await?;
+    connection
+        .emit_signal(
+            None::<&str>,
+            "/KGlobalSettings",
+            "org.kde.KGlobalSettings",
+            "notifyChange",
+            &(PALETTE_CHANGED, 0i32),
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn is_installed(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|directory| directory.join(program).is_file())
+    })
+}
+
+fn execute(command: &mut Command) -> anyhow::Result<()> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to launch '{program}'"))?;
+
+    if !status.success() {
+        return Err(anyhow!("'{program}' failed with {status}"));
+    }
+
+    Ok(())
+}
@@ -79,7 +79,7 @@ fn get_autostart_file_path(mkdirs: bool) -> Result<PathBuf, Error> {
         return Err(Error::InvalidAutostartDir(message));
     }
 
-    return Ok(xdg_autostart_dir.join("dark-mode-daemon.desktop"));
+    Ok(xdg_autostart_dir.join("dark-mode-daemon.desktop"))
 }
 
 pub fn install_autostart_xdg(
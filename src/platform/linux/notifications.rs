@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use zbus::{proxy, zvariant::Value, Connection};
+
+use crate::notifications::{Notification, NotificationServer};
+
+/// The name we show up with in notifications.
+const APP_NAME: &str = "Dark Mode Daemon";
+
+/// Notifications should stay until the user dismisses them, since nobody
+/// might be looking when a script fails.
+const NEVER_EXPIRE: i32 = 0;
+
+/// The `urgency` hint of a normal notification.
+const URGENCY_NORMAL: u8 = 1;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Shows notifications through the `org.freedesktop.Notifications` service
+/// of the desktop.
+pub struct FreedesktopNotifications {
+    proxy: NotificationsProxy<'static>,
+}
+
+impl FreedesktopNotifications {
+    /// Talks to the notification service on the session bus.
+    pub async fn session() -> anyhow::Result<Self> {
+        Self::new(&Connection::session().await?).await
+    }
+
+    pub async fn new(connection: &Connection) -> anyhow::Result<Self> {
+        Ok(Self {
+            proxy: NotificationsProxy::new(connection).await?,
+        })
+    }
+}
+
+impl NotificationServer for FreedesktopNotifications {
+    fn notify(
+        &self,
+        notification: Notification,
+        replaces_id: u32,
+    ) -> BoxFuture<'_, anyhow::Result<u32>> {
+        Box::pin(async move {
+            let hints = HashMap::from([("urgency", Value::from(URGENCY_NORMAL))]);
+            let id = self
+                .proxy
+                .notify(
+                    APP_NAME,
+                    replaces_id,
+                    "dialog-error",
+                    &notification.summary,
+                    &notification.body,
+                    &[],
+                    hints,
+                    NEVER_EXPIRE,
+                )
+                .await?;
+
+            Ok(id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::net::UnixStream;
+    use zbus::{connection::Builder, zvariant::OwnedValue, Guid};
+
+    use super::*;
+
+    /// What the mock service received for a single notification.
+    #[derive(Debug, PartialEq)]
+    struct Received {
+        app_name: String,
+        replaces_id: u32,
+        summary: String,
+        body: String,
+        urgency: Option<u8>,
+        expire_timeout: i32,
+    }
+
+    /// Stands in for the notification daemon of a desktop.
+    struct MockNotifications {
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockNotifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: String,
+            replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            expire_timeout: i32,
+        ) -> u32 {
+            let mut received = self.received.lock().unwrap();
+            received.push(Received {
+                app_name,
+                replaces_id,
+                summary,
+                body,
+                urgency: hints
+                    .get("urgency")
+                    .and_then(|urgency| u8::try_from(urgency).ok()),
+                expire_timeout,
+            });
+
+            received.len() as u32
+        }
+    }
+
+    /// Connects to a mock service over a socket pair, so no bus is needed.
+    async fn connect() -> (
+        FreedesktopNotifications,
+        Arc<Mutex<Vec<Received>>>,
+        Connection,
+    ) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mock = MockNotifications {
+            received: received.clone(),
+        };
+
+        let (server, client) = UnixStream::pair().unwrap();
+        let server = Builder::unix_stream(server)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at("/org/freedesktop/Notifications", mock)
+            .unwrap()
+            .build();
+        let client = Builder::unix_stream(client).p2p().build();
+        let (server, client) = futures::join!(server, client);
+
+        let notifications = FreedesktopNotifications::new(&client.unwrap())
+            .await
+            .unwrap();
+
+        (notifications, received, server.unwrap())
+    }
+
+    #[tokio::test]
+    async fn sends_notifications_to_the_service() {
+        let (notifications, received, _server) = connect().await;
+
+        let notification = Notification {
+            summary: "1 script failed".to_string(),
+            body: "10-kitty.sh: exit status 1".to_string(),
+        };
+        let id = notifications.notify(notification, 0).await.unwrap();
+
+        assert_eq!(id, 1);
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![Received {
+                app_name: APP_NAME.to_string(),
+                replaces_id: 0,
+                summary: "1 script failed".to_string(),
+                body: "10-kitty.sh: exit status 1".to_string(),
+                urgency: Some(URGENCY_NORMAL),
+                expire_timeout: NEVER_EXPIRE,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn replaces_previous_notifications() {
+        let (notifications, received, _server) = connect().await;
+
+        let notification = Notification {
+            summary: "1 script failed".to_string(),
+            body: String::new(),
+        };
+        let first = notifications.notify(notification.clone(), 0).await.unwrap();
+        notifications.notify(notification, first).await.unwrap();
+
+        let replaced: Vec<_> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|received| received.replaces_id)
+            .collect();
+        assert_eq!(replaced, vec![0, first]);
+    }
+}
@@ -0,0 +1,332 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use tokio::sync::{mpsc::UnboundedSender, oneshot, watch};
+use zbus::{connection::Builder, fdo, interface, object_server::SignalEmitter, Connection};
+
+use crate::{
+    daemon::Event,
+    ipc::{Override, OverrideExpiry, Pause, Request, Response, Status},
+    mode::ColorMode,
+};
+
+/// The well-known name the daemon owns on the session bus.
+pub const NAME: &str = "me.niclasve.DarkModeDaemon";
+
+/// Where the daemon can be found below its name.
+pub const PATH: &str = "/me/niclasve/DarkModeDaemon";
+
+/// Offers what the CLI can do over its socket to desktop widgets and
+/// extensions, which would rather talk D-Bus.
+pub struct DarkModeDaemon {
+    events: UnboundedSender<Event>,
+    status: watch::Receiver<Status>,
+}
+
+impl DarkModeDaemon {
+    pub fn new(events: UnboundedSender<Event>, status: watch::Receiver<Status>) -> Self {
+        Self { events, status }
+    }
+
+    /// Hands the request to the daemon, like the socket does for the CLI.
+    async fn request(&self, request: Request) -> fdo::Result<()> {
+        let (respond, response) = oneshot::channel();
+        self.events
+            .send(Event::Request(request, respond))
+            .map_err(|_| fdo::Error::Failed("The daemon is shutting down".to_string()))?;
+
+        match response.await {
+            Ok(Response::Error { message }) => Err(fdo::Error::Failed(message)),
+            Ok(_) => Ok(()),
+            Err(_) => Err(fdo::Error::Failed("The daemon did not respond".to_string())),
+        }
+    }
+}
+
+#[interface(name = "me.niclasve.DarkModeDaemon")]
+impl DarkModeDaemon {
+    /// Runs the scripts again for the current mode.
+    async fn rerun(&self) -> fdo::Result<()> {
+        self.request(Request::Rerun).await
+    }
+
+    /// Stops running scripts for the given number of seconds, or until
+    /// resumed for 0.
+    async fn pause(&self, seconds: u64) -> fdo::Result<()> {
+        let until = match seconds {
+            0 => None,
+            seconds => Some(from_now(seconds)?),
+        };
+        self.request(Request::Pause(Pause { until })).await
+    }
+
+    /// Ends a pause, running the scripts if the mode changed in the meantime.
+    async fn resume(&self) -> fdo::Result<()> {
+        self.request(Request::Resume { run_scripts: true }).await
+    }
+
+    /// Runs the scripts for `light` or `dark` for the given number of
+    /// seconds, or until the next change for 0. An empty mode ends the
+    /// override.
+    #[zbus(name = "Override")]
+    async fn override_mode(&self, mode: &str, seconds: u64) -> fdo::Result<()> {
+        if mode.is_empty() {
+            return self.request(Request::ClearOverride).await;
+        }
+
+        let mode = ColorMode::from_str(mode, true).map_err(fdo::Error::InvalidArgs)?;
+        let expiry = match seconds {
+            0 => OverrideExpiry::NextChange,
+            seconds => OverrideExpiry::At(from_now(seconds)?),
+        };
+        self.request(Request::Override(Override { mode, expiry }))
+            .await
+    }
+
+    /// The mode the scripts were last run for.
+    #[zbus(property)]
+    fn current_mode(&self) -> String {
+        self.status.borrow().applied_mode.to_string()
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.status.borrow().paused.is_some()
+    }
+
+    /// When the scripts last finished running, in seconds since the epoch.
+    #[zbus(property)]
+    fn last_run(&self) -> i64 {
+        self.status
+            .borrow()
+            .last_run
+            .map_or(0, |last_run| last_run.timestamp())
+    }
+
+    /// The scripts finished running for the mode.
+    #[zbus(signal)]
+    async fn mode_applied(emitter: &SignalEmitter<'_>, mode: &str) -> zbus::Result<()>;
+}
+
+/// The time the given number of seconds from now, unless that is too far in
+/// the future to represent.
+fn from_now(seconds: u64) -> fdo::Result<DateTime<Utc>> {
+    i64::try_from(seconds)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .and_then(|delta| Utc::now().checked_add_signed(delta))
+        .ok_or_else(|| fdo::Error::InvalidArgs(format!("{seconds} seconds is too long")))
+}
+
+/// Takes the well-known name on the session bus and serves the daemon until
+/// the returned connection is dropped.
+pub async fn serve(
+    events: UnboundedSender<Event>,
+    status: watch::Receiver<Status>,
+) -> anyhow::Result<Connection> {
+    let builder = Builder::session()?.name(NAME)?;
+    serve_on(builder, events, status).await
+}
+
+async fn serve_on(
+    builder: Builder<'_>,
+    events: UnboundedSender<Event>,
+    status: watch::Receiver<Status>,
+) -> anyhow::Result<Connection> {
+    let connection = builder
+        .serve_at(PATH, DarkModeDaemon::new(events, status.clone()))?
+        .build()
+        .await?;
+    tokio::spawn(announce(connection.clone(), status));
+
+    Ok(connection)
+}
+
+/// Emits `PropertiesChanged` and `ModeApplied` as the status of the daemon
+/// changes.
+async fn announce(connection: Connection, mut status: watch::Receiver<Status>) {
+    let Ok(interface) = connection
+        .object_server()
+        .interface::<_, DarkModeDaemon>(PATH)
+        .await
+    else {
+        return;
+    };
+    let emitter = interface.signal_emitter();
+
+    let mut previous = status.borrow_and_update().clone();
+    while status.changed().await.is_ok() {
+        let current = status.borrow_and_update().clone();
+        let daemon = interface.get().await;
+
+        if current.applied_mode != previous.applied_mode {
+            let _ = daemon.current_mode_changed(emitter).await;
+        }
+        if current.paused.is_some() != previous.paused.is_some() {
+            let _ = daemon.paused_changed(emitter).await;
+        }
+        if current.last_run != previous.last_run {
+            let _ = daemon.last_run_changed(emitter).await;
+            let mode = current.applied_mode.to_string();
+            let _ = DarkModeDaemon::mode_applied(emitter, &mode).await;
+        }
+
+        previous = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::StreamExt;
+    use tokio::{net::UnixStream, sync::mpsc};
+    use zbus::{proxy, Guid};
+
+    use super::*;
+
+    #[proxy(
+        interface = "me.niclasve.DarkModeDaemon",
+        default_service = "me.niclasve.DarkModeDaemon",
+        default_path = "/me/niclasve/DarkModeDaemon"
+    )]
+    trait DarkModeDaemon {
+        fn rerun(&self) -> zbus::Result<()>;
+        fn pause(&self, seconds: u64) -> zbus::Result<()>;
+        fn resume(&self) -> zbus::Result<()>;
+        fn override_(&self, mode: &str, seconds: u64) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn current_mode(&self) -> zbus::Result<String>;
+        #[zbus(property)]
+        fn paused(&self) -> zbus::Result<bool>;
+        #[zbus(property)]
+        fn last_run(&self) -> zbus::Result<i64>;
+
+        #[zbus(signal)]
+        fn mode_applied(&self, mode: &str) -> zbus::Result<()>;
+    }
+
+    fn initial_status() -> Status {
+        Status {
+            detected_mode: ColorMode::Light,
+            applied_mode: ColorMode::Light,
+            active_override: None,
+            paused: None,
+            last_run: None,
+        }
+    }
+
+    /// Serves the daemon over a socket pair, with a stand-in for its state
+    /// that records requests and refuses to rerun.
+    async fn connect() -> (
+        DarkModeDaemonProxy<'static>,
+        Arc<Mutex<Vec<Request>>>,
+        watch::Sender<Status>,
+        Connection,
+    ) {
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Some(Event::Request(request, respond)) = receiver.recv().await {
+                let response = match request {
+                    Request::Rerun => Response::Error {
+                        message: "The daemon is paused".to_string(),
+                    },
+                    _ => Response::Status(initial_status()),
+                };
+                recorded.lock().unwrap().push(request);
+                let _ = respond.send(response);
+            }
+        });
+        let (status, receiver) = watch::channel(initial_status());
+
+        let (server, client) = UnixStream::pair().unwrap();
+        let server = Builder::unix_stream(server)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p();
+        let client = Builder::unix_stream(client).p2p().build();
+        let (server, client) = futures::join!(serve_on(server, events, receiver), client);
+
+        let proxy = DarkModeDaemonProxy::new(&client.unwrap()).await.unwrap();
+
+        (proxy, requests, status, server.unwrap())
+    }
+
+    #[tokio::test]
+    async fn forwards_methods_as_requests() {
+        let (daemon, requests, _status, _server) = connect().await;
+
+        daemon.pause(0).await.unwrap();
+        daemon.resume().await.unwrap();
+        daemon.override_("Dark", 0).await.unwrap();
+        daemon.override_("", 0).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(matches!(requests[0], Request::Pause(Pause { until: None })));
+        assert!(matches!(requests[1], Request::Resume { run_scripts: true }));
+        assert!(matches!(
+            requests[2],
+            Request::Override(Override {
+                mode: ColorMode::Dark,
+                expiry: OverrideExpiry::NextChange
+            })
+        ));
+        assert!(matches!(requests[3], Request::ClearOverride));
+    }
+
+    #[tokio::test]
+    async fn reports_errors() {
+        let (daemon, requests, _status, _server) = connect().await;
+
+        let error = daemon.rerun().await.unwrap_err();
+        assert!(
+            error.to_string().contains("The daemon is paused"),
+            "{error}"
+        );
+
+        let error = daemon.override_("blue", 0).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                zbus::Error::MethodError(ref name, _, _)
+                    if name.as_str() == "org.freedesktop.DBus.Error.InvalidArgs"
+            ),
+            "{error}"
+        );
+
+        let error = daemon.pause(u64::MAX).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                zbus::Error::MethodError(ref name, _, _)
+                    if name.as_str() == "org.freedesktop.DBus.Error.InvalidArgs"
+            ),
+            "{error}"
+        );
+        assert!(daemon.override_("dark", u64::MAX).await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn announces_applied_modes() {
+        let (daemon, _requests, status, _server) = connect().await;
+        assert_eq!(daemon.current_mode().await.unwrap(), "light");
+        assert_eq!(daemon.last_run().await.unwrap(), 0);
+
+        let mut applied = daemon.receive_mode_applied().await.unwrap();
+        let last_run = Utc::now();
+        status.send_replace(Status {
+            applied_mode: ColorMode::Dark,
+            last_run: Some(last_run),
+            ..initial_status()
+        });
+
+        let signal = applied.next().await.unwrap();
+        assert_eq!(signal.args().unwrap().mode, "dark");
+        assert_eq!(daemon.current_mode().await.unwrap(), "dark");
+        assert_eq!(daemon.last_run().await.unwrap(), last_run.timestamp());
+        assert!(!daemon.paused().await.unwrap());
+    }
+}
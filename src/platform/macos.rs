@@ -1,8 +1,9 @@
+pub mod appearance;
 pub mod autostart;
 
 use crate::{
-    mode::ColorMode,
-    platform::{ColorModeDaemon, ColorModeDetector},
+    mode::{Appearance, ColorMode},
+    platform::{ColorModeDaemon, ColorModeDetector, ColorModeEvent},
 };
 use block2::RcBlock;
 use objc2_app_kit::NSApplication;
@@ -20,11 +21,13 @@ impl ColorModeDetector for MacOsColorModeDetector {
 impl ColorModeDaemon for MacOsColorModeDetector {
     async fn on_color_changed<F>(&self, callback: F)
     where
-        F: Fn(ColorMode) + 'static,
+        F: Fn(ColorModeEvent) + 'static,
     {
         let execute_callback = RcBlock::new(move |_| {
             // FIXME: Error handling
-            callback(current_mode().unwrap());
+            callback(ColorModeEvent::AppearanceChanged(Appearance::from(
+                current_mode().unwrap(),
+            )));
         });
 
         unsafe {
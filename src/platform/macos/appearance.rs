@@ -0,0 +1,28 @@
+use std::process::Command;
+
+use anyhow::{anyhow, Context};
+
+use crate::mode::ColorMode;
+
+/// Changes the color scheme of the whole system.
+pub async fn set_color_mode(mode: ColorMode) -> anyhow::Result<()> {
+    let dark_mode = match mode {
+        ColorMode::Light => "false",
+        ColorMode::Dark => "true",
+    };
+    let script = format!(
+        "tell application \"System Events\" to tell appearance preferences to set dark mode to {dark_mode}"
+    );
+
+    let status = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .status()
+        .context("Failed to launch 'osascript'")?;
+
+    if !status.success() {
+        return Err(anyhow!("'osascript' failed with {status}"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,403 @@
+use std::{f64::consts::PI, time::Duration};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+
+use crate::{
+    config::{Config, ScheduleConfig},
+    mode::{Appearance, ColorMode},
+    platform::{ColorModeDaemon, ColorModeDetector, ColorModeEvent},
+};
+
+/// The longest we sleep before looking at the clock again.
+///
+/// Timers run on a monotonic clock, which neither follows changes to the wall
+/// clock nor (on Linux) advances while the machine is suspended. Waking up
+/// regularly means we notice both within a minute.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// When to switch between light and dark mode.
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    /// Switch at the same local time every day.
+    Fixed { light: NaiveTime, dark: NaiveTime },
+
+    /// Switch at (shifted) sunrise and sunset for the given location.
+    Sun {
+        latitude: f64,
+        longitude: f64,
+        sunrise_offset: TimeDelta,
+        sunset_offset: TimeDelta,
+    },
+}
+
+/// What the sun does on a given day.
+enum Daylight {
+    Transitions {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    MidnightSun,
+    PolarNight,
+}
+
+impl TryFrom<ScheduleConfig> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(config: ScheduleConfig) -> Result<Self, Self::Error> {
+        match config {
+            ScheduleConfig::Fixed { light, dark } => Ok(Schedule::Fixed {
+                light: parse_time_of_day(&light)?,
+                dark: parse_time_of_day(&dark)?,
+            }),
+            ScheduleConfig::Sun {
+                latitude,
+                longitude,
+                sunrise_offset,
+                sunset_offset,
+            } => {
+                if !(-90.0..=90.0).contains(&latitude) {
+                    return Err(anyhow!("Latitude {latitude} is not between -90 and 90"));
+                }
+                if !(-180.0..=180.0).contains(&longitude) {
+                    return Err(anyhow!("Longitude {longitude} is not between -180 and 180"));
+                }
+
+                Ok(Schedule::Sun {
+                    latitude,
+                    longitude,
+                    sunrise_offset: parse_offset(sunrise_offset.as_deref())?,
+                    sunset_offset: parse_offset(sunset_offset.as_deref())?,
+                })
+            }
+        }
+    }
+}
+
+impl Schedule {
+    /// The schedule the `schedule` detector should follow.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        config
+            .schedule
+            .clone()
+            .context("The schedule detector requires a [schedule] section in the config")?
+            .try_into()
+    }
+
+    /// The mode that should be active at the given point in time.
+    pub fn mode_at(&self, now: DateTime<Local>) -> ColorMode {
+        match self {
+            Schedule::Fixed { light, dark } => {
+                let time = now.time();
+                let is_light = if light <= dark {
+                    *light <= time && time < *dark
+                } else {
+                    *light <= time || time < *dark
+                };
+
+                if is_light {
+                    ColorMode::Light
+                } else {
+                    ColorMode::Dark
+                }
+            }
+            Schedule::Sun { .. } => match self.daylight(now.date_naive()) {
+                Daylight::MidnightSun => ColorMode::Light,
+                Daylight::PolarNight => ColorMode::Dark,
+                Daylight::Transitions { sunrise, sunset } => {
+                    if sunrise <= now && now < sunset {
+                        ColorMode::Light
+                    } else {
+                        ColorMode::Dark
+                    }
+                }
+            },
+        }
+    }
+
+    /// The next point in time after `now` at which the mode switches, if it
+    /// switches today or tomorrow.
+    pub fn next_switch(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let today = now.date_naive();
+        let tomorrow = today.checked_add_days(Days::new(1))?;
+
+        [today, tomorrow]
+            .into_iter()
+            .flat_map(|date| self.switches_on(date))
+            .filter(|switch| *switch > now)
+            .min()
+    }
+
+    fn switches_on(&self, date: NaiveDate) -> Vec<DateTime<Local>> {
+        match self {
+            Schedule::Fixed { light, dark } => [light, dark]
+                .into_iter()
+                // Skips times that do not exist because of daylight saving time.
+                .filter_map(|time| Local.from_local_datetime(&date.and_time(*time)).earliest())
+                .collect(),
+            Schedule::Sun { .. } => match self.daylight(date) {
+                Daylight::Transitions { sunrise, sunset } => {
+                    vec![sunrise.with_timezone(&Local), sunset.with_timezone(&Local)]
+                }
+                Daylight::MidnightSun | Daylight::PolarNight => Vec::new(),
+            },
+        }
+    }
+
+    fn daylight(&self, date: NaiveDate) -> Daylight {
+        let Schedule::Sun {
+            latitude,
+            longitude,
+            sunrise_offset,
+            sunset_offset,
+        } = self
+        else {
+            unreachable!("only sun based schedules have daylight");
+        };
+
+        match sun_transitions(date, *latitude, *longitude) {
+            Daylight::Transitions { sunrise, sunset } => Daylight::Transitions {
+                sunrise: sunrise + *sunrise_offset,
+                sunset: sunset + *sunset_offset,
+            },
+            other => other,
+        }
+    }
+}
+
+/// Computes sunrise and sunset using the
+/// [sunrise equation](https://en.wikipedia.org/wiki/Sunrise_equation), which
+/// is accurate to about a minute. This is plenty for switching color schemes
+/// and works without any network access.
+fn sun_transitions(date: NaiveDate, latitude: f64, longitude: f64) -> Daylight {
+    let radians = |degrees: f64| degrees * PI / 180.0;
+    let degrees = |radians: f64| radians * 180.0 / PI;
+
+    let days_since_unix_epoch = date.signed_duration_since(NaiveDate::default()).num_days();
+    let julian_day = days_since_unix_epoch as f64 + 2440587.5;
+    let days_since_j2000 = (julian_day - 2451545.0 + 0.0008).ceil();
+
+    let mean_solar_time = days_since_j2000 - longitude / 360.0;
+    let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+    let center = 1.9148 * radians(mean_anomaly).sin()
+        + 0.0200 * radians(2.0 * mean_anomaly).sin()
+        + 0.0003 * radians(3.0 * mean_anomaly).sin();
+    let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let solar_transit = 2451545.0 + mean_solar_time + 0.0053 * radians(mean_anomaly).sin()
+        - 0.0069 * radians(2.0 * ecliptic_longitude).sin();
+
+    let declination = (radians(ecliptic_longitude).sin() * radians(23.4397).sin()).asin();
+    let cos_hour_angle = (radians(-0.833).sin() - radians(latitude).sin() * declination.sin())
+        / (radians(latitude).cos() * declination.cos());
+
+    if cos_hour_angle < -1.0 {
+        return Daylight::MidnightSun;
+    }
+    if cos_hour_angle > 1.0 {
+        return Daylight::PolarNight;
+    }
+
+    let hour_angle = degrees(cos_hour_angle.acos());
+    let from_julian = |julian: f64| {
+        let seconds = ((julian - 2440587.5) * 86400.0).round() as i64;
+        DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+    };
+
+    Daylight::Transitions {
+        sunrise: from_julian(solar_transit - hour_angle / 360.0),
+        sunset: from_julian(solar_transit + hour_angle / 360.0),
+    }
+}
+
+fn parse_time_of_day(value: &str) -> anyhow::Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .with_context(|| format!("'{value}' is not a valid time of day (expected HH:MM)"))
+}
+
+/// Parses offsets like `30m`, `+1h 15m` or `-45m`.
+fn parse_offset(value: Option<&str>) -> anyhow::Result<TimeDelta> {
+    let Some(value) = value.map(str::trim) else {
+        return Ok(TimeDelta::zero());
+    };
+
+    let (negative, magnitude) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let duration = humantime::parse_duration(magnitude.trim())
+        .with_context(|| format!("'{value}' is not a valid offset (e.g. '30m' or '-1h')"))?;
+    let offset = TimeDelta::from_std(duration)?;
+
+    Ok(if negative { -offset } else { offset })
+}
+
+/// Derives the color mode from the time of day instead of asking the OS.
+pub struct ScheduleColorModeDetector {
+    schedule: Schedule,
+}
+
+impl ScheduleColorModeDetector {
+    pub fn new(schedule: Schedule) -> Self {
+        Self { schedule }
+    }
+}
+
+impl ColorModeDetector for ScheduleColorModeDetector {
+    async fn current_mode(&self) -> anyhow::Result<ColorMode> {
+        Ok(self.schedule.mode_at(Local::now()))
+    }
+}
+
+impl ColorModeDaemon for ScheduleColorModeDetector {
+    async fn on_color_changed<F>(&self, callback: F)
+    where
+        F: Fn(ColorModeEvent),
+    {
+        let mut previous_mode = self.schedule.mode_at(Local::now());
+        loop {
+            let now = Local::now();
+            let sleep_duration = self
+                .schedule
+                .next_switch(now)
+                .and_then(|switch| (switch - now).to_std().ok())
+                .map_or(MAX_SLEEP, |until_switch| until_switch.min(MAX_SLEEP));
+            tokio::time::sleep(sleep_duration).await;
+
+            // We always compare against the wall clock, so jumps of the system
+            // time or a suspended machine still result in the correct mode.
+            let current_mode = self.schedule.mode_at(Local::now());
+            if current_mode != previous_mode {
+                previous_mode = current_mode;
+                callback(ColorModeEvent::AppearanceChanged(Appearance::from(
+                    current_mode,
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(date: &str, time: &str) -> DateTime<Utc> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        date.and_time(parse_time_of_day(time).unwrap()).and_utc()
+    }
+
+    fn local(date: &str, time: &str) -> DateTime<Local> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let time = parse_time_of_day(time).unwrap();
+        Local.from_local_datetime(&date.and_time(time)).unwrap()
+    }
+
+    fn sun(latitude: f64, longitude: f64) -> Schedule {
+        Schedule::Sun {
+            latitude,
+            longitude,
+            sunrise_offset: TimeDelta::zero(),
+            sunset_offset: TimeDelta::zero(),
+        }
+    }
+
+    fn assert_close(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        let difference = (actual - expected).abs();
+        assert!(
+            difference <= TimeDelta::minutes(2),
+            "{actual} is not within two minutes of {expected}"
+        );
+    }
+
+    #[test]
+    fn computes_sunrise_and_sunset() {
+        // Berlin on the summer solstice: sunrise at 4:43, sunset at 21:33 CEST.
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let Daylight::Transitions { sunrise, sunset } = sun_transitions(date, 52.52, 13.405) else {
+            panic!("The sun rises and sets in Berlin");
+        };
+
+        assert_close(sunrise, utc("2024-06-21", "02:43"));
+        assert_close(sunset, utc("2024-06-21", "19:33"));
+    }
+
+    #[test]
+    fn follows_the_sun() {
+        let berlin = Schedule::Sun {
+            latitude: 52.52,
+            longitude: 13.405,
+            sunrise_offset: TimeDelta::zero(),
+            sunset_offset: TimeDelta::hours(1),
+        };
+
+        let noon = utc("2024-06-21", "12:00").with_timezone(&Local);
+        assert_eq!(berlin.mode_at(noon), ColorMode::Light);
+        let night = utc("2024-06-21", "23:00").with_timezone(&Local);
+        assert_eq!(berlin.mode_at(night), ColorMode::Dark);
+
+        // The offset moves the switch to dark mode an hour past sunset.
+        let next_switch = berlin.next_switch(noon).unwrap().with_timezone(&Utc);
+        assert_close(next_switch, utc("2024-06-21", "20:33"));
+    }
+
+    #[test]
+    fn handles_midnight_sun_and_polar_night() {
+        let tromso = sun(69.65, 18.96);
+
+        let summer = utc("2024-06-21", "00:00").with_timezone(&Local);
+        assert_eq!(tromso.mode_at(summer), ColorMode::Light);
+        assert_eq!(tromso.next_switch(summer), None);
+
+        let winter = utc("2024-12-21", "12:00").with_timezone(&Local);
+        assert_eq!(tromso.mode_at(winter), ColorMode::Dark);
+        assert_eq!(tromso.next_switch(winter), None);
+    }
+
+    #[test]
+    fn switches_at_fixed_times() {
+        let schedule = Schedule::Fixed {
+            light: parse_time_of_day("07:00").unwrap(),
+            dark: parse_time_of_day("19:30").unwrap(),
+        };
+
+        assert_eq!(
+            schedule.mode_at(local("2024-03-01", "06:59")),
+            ColorMode::Dark
+        );
+        assert_eq!(
+            schedule.mode_at(local("2024-03-01", "07:00")),
+            ColorMode::Light
+        );
+        assert_eq!(
+            schedule.next_switch(local("2024-03-01", "12:00")),
+            Some(local("2024-03-01", "19:30"))
+        );
+    }
+
+    #[test]
+    fn wraps_fixed_times_around_midnight() {
+        // Light mode at night, e.g. for night shifts.
+        let schedule = Schedule::Fixed {
+            light: parse_time_of_day("22:00").unwrap(),
+            dark: parse_time_of_day("06:00").unwrap(),
+        };
+
+        assert_eq!(
+            schedule.mode_at(local("2024-03-01", "23:00")),
+            ColorMode::Light
+        );
+        assert_eq!(
+            schedule.mode_at(local("2024-03-01", "03:00")),
+            ColorMode::Light
+        );
+        assert_eq!(
+            schedule.mode_at(local("2024-03-01", "12:00")),
+            ColorMode::Dark
+        );
+        assert_eq!(
+            schedule.next_switch(local("2024-03-01", "23:00")),
+            Some(local("2024-03-02", "06:00"))
+        );
+    }
+}
@@ -0,0 +1,74 @@
+/// An MQTT broker receiving the current mode and every change.
+pub mod mqtt;
+/// HTTP endpoints receiving a `POST` request for every change.
+pub mod webhook;
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use self::mqtt::MqttPublisher;
+use crate::{
+    cli::commands::RunContext,
+    config::{Config, WebhookConfig},
+};
+
+/// Everything outside of the scripts that is told about changes.
+pub struct Sinks {
+    webhooks: Vec<WebhookConfig>,
+    mqtt: Option<MqttPublisher>,
+}
+
+impl Sinks {
+    /// Sets up the sinks of the config, connecting to the MQTT broker in the
+    /// background.
+    ///
+    /// Like the scripts, sinks never keep the daemon from running. Problems,
+    /// like a password that can't be read, are logged instead.
+    pub fn connect(config: &Config) -> Self {
+        let mqtt = config
+            .mqtt
+            .as_ref()
+            .and_then(|mqtt| match MqttPublisher::connect(mqtt) {
+                Ok(publisher) => Some(publisher),
+                Err(error) => {
+                    println!("❌ Not publishing to MQTT: {error:#}");
+                    None
+                }
+            });
+
+        Self {
+            webhooks: config.webhooks.clone(),
+            mqtt,
+        }
+    }
+
+    /// Tells the sinks about the run with the given ID, without waiting for
+    /// them to answer.
+    pub fn publish(&self, context: &RunContext, run_id: Uuid) {
+        if let Some(mqtt) = &self.mqtt {
+            mqtt.publish(context, run_id);
+        }
+
+        let body = Arc::new(context.payload(run_id));
+        for webhook in &self.webhooks {
+            if context.accent_color_only && !webhook.on_accent_color_change {
+                continue;
+            }
+
+            let webhook = webhook.clone();
+            let body = body.clone();
+            tokio::task::spawn_blocking(move || match webhook::send(&webhook, &body) {
+                Ok(()) => println!("✅ Webhook '{}' succeeded!", webhook.name()),
+                Err(error) => println!("❌ Webhook '{}' failed: {error:#}", webhook.name()),
+            });
+        }
+    }
+
+    /// Marks the daemon as offline where that is possible.
+    pub async fn shutdown(self) {
+        if let Some(mqtt) = self.mqtt {
+            mqtt.disconnect().await;
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    cli::commands::RunContext,
+    config::{MqttConfig, SecretValue},
+};
+
+/// How many messages may wait while the broker is unreachable.
+const CAPACITY: usize = 16;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// How long to wait before connecting again, after the connection to the
+/// broker failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How long shutting down may wait for the broker.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// Publishes what the daemon does to an MQTT broker:
+///
+/// - `<topic>/mode` is the mode the scripts were last run for (retained)
+/// - `<topic>/events` receives the JSON document scripts get on stdin for
+///   every run
+/// - `<topic>/status` is `online`, or `offline` once the daemon stopped or
+///   lost its connection (retained)
+pub struct MqttPublisher {
+    client: AsyncClient,
+    topic: String,
+    on_accent_color_change: bool,
+    connection: JoinHandle<()>,
+}
+
+impl MqttPublisher {
+    /// Connects to the broker in the background, and keeps reconnecting
+    /// whenever the connection is lost.
+    pub fn connect(config: &MqttConfig) -> anyhow::Result<Self> {
+        let status = format!("{}/status", config.topic);
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(KEEP_ALIVE);
+        options.set_last_will(LastWill::new(&status, OFFLINE, QoS::AtLeastOnce, true));
+        if let Some(username) = &config.username {
+            let password = config
+                .password
+                .as_ref()
+                .map(SecretValue::resolve)
+                .transpose()?
+                .unwrap_or_default();
+            options.set_credentials(username, password);
+        }
+
+        let (client, eventloop) = AsyncClient::new(options, CAPACITY);
+        // Queued right away, so it is the first thing the broker sees.
+        client.try_publish(&status, QoS::AtLeastOnce, true, ONLINE)?;
+        let connection = tokio::spawn(poll(eventloop, client.clone(), status));
+
+        Ok(Self {
+            client,
+            topic: config.topic.clone(),
+            on_accent_color_change: config.on_accent_color_change,
+            connection,
+        })
+    }
+
+    /// Publishes the run with the given ID, without waiting for the broker.
+    ///
+    /// Runs for nothing but a new accent color leave the mode as it is, and
+    /// are only published as events when opted into.
+    pub fn publish(&self, context: &RunContext, run_id: Uuid) {
+        if context.accent_color_only {
+            if self.on_accent_color_change {
+                self.send("events", false, context.payload(run_id));
+            }
+            return;
+        }
+
+        self.send("mode", true, context.appearance.mode.to_string());
+        self.send("events", false, context.payload(run_id));
+    }
+
+    /// Marks the daemon as offline and disconnects, giving up if the broker
+    /// takes too long.
+    pub async fn disconnect(self) {
+        let status = format!("{}/status", self.topic);
+        let _ = self
+            .client
+            .try_publish(status, QoS::AtLeastOnce, true, OFFLINE);
+        let _ = self.client.try_disconnect();
+        let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, self.connection).await;
+    }
+
+    fn send(&self, subtopic: &str, retain: bool, payload: String) {
+        let topic = format!("{}/{subtopic}", self.topic);
+        if let Err(error) = self
+            .client
+            .try_publish(&topic, QoS::AtLeastOnce, retain, payload)
+        {
+            println!("❌ Could not publish to MQTT topic '{topic}': {error}");
+        }
+    }
+}
+
+/// Drives the connection until the client disconnects.
+///
+/// After reconnecting, the broker already published our last will, so we
+/// announce being online again.
+async fn poll(mut eventloop: EventLoop, client: AsyncClient, status: String) {
+    let mut reconnecting = false;
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                println!("📡 Connected to the MQTT broker");
+                if reconnecting {
+                    let _ = client.try_publish(&status, QoS::AtLeastOnce, true, ONLINE);
+                }
+                reconnecting = true;
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+            Ok(_) => {}
+            Err(error) => {
+                println!(
+                    "❌ MQTT connection failed, retrying in {}: {error}",
+                    humantime::format_duration(RECONNECT_DELAY)
+                );
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck, Publish};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{cli::commands::Trigger, mode::Appearance, mode::ColorMode};
+
+    /// Accepts a single client like a broker would, and returns every packet
+    /// it sent until it disconnected.
+    async fn broker(listener: TcpListener) -> Vec<Packet> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buffer = BytesMut::new();
+        let mut packets = Vec::new();
+
+        loop {
+            let packet = match Packet::read(&mut buffer, 1024 * 1024) {
+                Ok(packet) => packet,
+                Err(_) => {
+                    if stream.read_buf(&mut buffer).await.unwrap() == 0 {
+                        return packets;
+                    }
+                    continue;
+                }
+            };
+
+            let mut response = BytesMut::new();
+            match &packet {
+                Packet::Connect(_) => {
+                    ConnAck::new(ConnectReturnCode::Success, false)
+                        .write(&mut response)
+                        .unwrap();
+                }
+                Packet::Publish(publish) if publish.qos == QoS::AtLeastOnce => {
+                    PubAck::new(publish.pkid).write(&mut response).unwrap();
+                }
+                _ => {}
+            }
+            // The client may hang up without waiting for our answers.
+            let _ = stream.write_all(&response).await;
+
+            let is_disconnect = packet == Packet::Disconnect;
+            packets.push(packet);
+            if is_disconnect {
+                return packets;
+            }
+        }
+    }
+
+    fn published(packets: &[Packet]) -> Vec<(String, String, bool)> {
+        packets
+            .iter()
+            .filter_map(|packet| match packet {
+                Packet::Publish(Publish {
+                    topic,
+                    payload,
+                    retain,
+                    ..
+                }) => Some((
+                    topic.clone(),
+                    String::from_utf8_lossy(payload).to_string(),
+                    *retain,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn publishes_the_mode_and_events() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = MqttConfig {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+            topic: "desk".to_string(),
+            client_id: "test".to_string(),
+            username: Some("me".to_string()),
+            password: Some(SecretValue::Plain("secret".to_string())),
+            on_accent_color_change: true,
+        };
+        let broker = tokio::spawn(broker(listener));
+
+        let publisher = MqttPublisher::connect(&config).unwrap();
+        let context = RunContext::new(Appearance::from(ColorMode::Dark), Trigger::Change);
+        let run_id = Uuid::new_v4();
+        publisher.publish(&context, run_id);
+        publisher.publish(
+            &RunContext {
+                accent_color_only: true,
+                ..context
+            },
+            run_id,
+        );
+        publisher.disconnect().await;
+        let packets = broker.await.unwrap();
+
+        let Packet::Connect(connect) = &packets[0] else {
+            panic!("Expected to connect first, got {:?}", packets[0]);
+        };
+        let will = connect.last_will.as_ref().unwrap();
+        assert_eq!(will.topic, "desk/status");
+        assert_eq!(&will.message[..], b"offline");
+        assert!(will.retain);
+        assert_eq!(connect.login.as_ref().unwrap().username, "me");
+
+        let published = published(&packets);
+        let modes: Vec<_> = published
+            .iter()
+            .filter(|(topic, _, _)| topic == "desk/mode")
+            .collect();
+        assert_eq!(
+            modes,
+            vec![&("desk/mode".to_string(), "dark".to_string(), true)]
+        );
+
+        let events: Vec<_> = published
+            .iter()
+            .filter(|(topic, _, _)| topic == "desk/events")
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|(_, payload, retain)| { !retain && payload.contains(&run_id.to_string()) }));
+
+        let statuses: Vec<_> = published
+            .iter()
+            .filter(|(topic, _, _)| topic == "desk/status")
+            .map(|(_, payload, retain)| (payload.as_str(), *retain))
+            .collect();
+        assert_eq!(statuses, vec![("online", true), ("offline", true)]);
+        assert_eq!(packets.last(), Some(&Packet::Disconnect));
+    }
+}